@@ -1,6 +1,7 @@
 use machinetree_core::{
     self,
-    node::{Component, NodeSeed},
+    key::Seed,
+    node::Component,
     node_host::{NodeControl, NodeHost},
 };
 
@@ -17,6 +18,7 @@ struct Param {
 
 impl Component for ExampleComponent {
     type Input = Param;
+    type Message = ();
 
     fn construct(input: &Self::Input) -> Self
     where
@@ -27,21 +29,20 @@ impl Component for ExampleComponent {
         }
     }
 
-    fn step(&mut self, control: &mut NodeControl, param: &Self::Input) -> Vec<NodeSeed> {
+    fn step(&mut self, control: &mut NodeControl, param: &Self::Input) -> Vec<Seed> {
         let self_index = param.self_index;
         let parent_index_chain = &param.parent_index_chain;
         let self_index_chain = format!("{parent_index_chain}/{self_index}");
         let current_child_count = self.child_count;
 
         if self.child_count > 0 {
-            self.child_count = self.child_count - 1;
+            self.child_count -= 1;
             control.rerender();
         }
 
         println!("{self_index_chain}",);
 
         (0..current_child_count)
-            .into_iter()
             .map(|index| {
                 Self::seed(
                     Param {
@@ -63,17 +64,8 @@ fn main() {
             self_index: 0,
             child_count: 4,
         },
-        format!(""),
+        String::new(),
     ));
-    let mut i = 0;
-    while {
-        println!("===start-iteration:{i}");
-        let render_report = host.render();
-        // println!("{}", &render_report);
-        println!("===end-iteration:{i}");
-
-        render_report.rendered_keys.len() > 0
-    } {
-        i += 1;
-    }
+    let render_report = host.run_until_idle();
+    println!("{}", &render_report);
 }
@@ -0,0 +1,149 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::Poll,
+};
+
+use crate::{
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::NodeControl,
+};
+
+type BoxedRun = Pin<Box<dyn Future<Output = Vec<Seed>>>>;
+
+// A machine written as a future, for machines that go through their steps sequentially.
+// The host polls the future on the node's steps and steps the node again whenever the future
+// is woken. Children emitted through the AsyncScope stay mounted while the future is pending,
+// and are replaced by the ones it resolves to.
+pub trait AsyncComponent
+where
+    Self: Sized + 'static,
+{
+    type Input: Sized + Clone + 'static;
+
+    fn construct(input: &Self::Input) -> Self;
+
+    fn run(
+        &mut self,
+        scope: AsyncScope,
+        input: &Self::Input,
+    ) -> impl Future<Output = Vec<Seed>> + 'static;
+
+    // Called when the parent rerenders with a new input for this node.
+    // Returning true drops the running future, keeping its children, and runs the new input.
+    // By default the running future keeps going with the input it started with,
+    // `input_changed` restarts it on inputs that are PartialEq.
+    fn should_restart(&self, _old_input: &Self::Input, _new_input: &Self::Input) -> bool {
+        false
+    }
+
+    fn seed(input: Self::Input, key: String) -> Seed {
+        AsyncNode::<Self>::seed(input, key)
+    }
+}
+
+// Given to AsyncComponent::run, usable from within the future
+#[derive(Clone)]
+pub struct AsyncScope {
+    emitted: Rc<RefCell<Option<Vec<Seed>>>>,
+    handle: NodeHandle,
+}
+
+impl AsyncScope {
+    // Replace the node's children without waiting for the future to resolve
+    pub fn emit(&self, children: Vec<Seed>) {
+        *self.emitted.borrow_mut() = Some(children);
+        let _ = self.handle.rerender();
+    }
+
+    pub fn handle(&self) -> &NodeHandle {
+        &self.handle
+    }
+}
+
+// Component driving an AsyncComponent, seeded through AsyncComponent::seed.
+// The running future cannot be written to a snapshot, neither can the node.
+pub struct AsyncNode<Machine: AsyncComponent> {
+    machine: Machine,
+    running: Option<BoxedRun>,
+    started: bool,
+    // Set by should_step, so that only the step following an accepted input restarts
+    restart_pending: Cell<bool>,
+    emitted: Rc<RefCell<Option<Vec<Seed>>>>,
+    children: Vec<Seed>,
+}
+
+impl<Machine> AsyncNode<Machine>
+where
+    Machine: AsyncComponent,
+{
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn start(&mut self, control: &NodeControl, input: &Machine::Input) {
+        // Children emitted by the dropped future are left behind
+        self.emitted = Default::default();
+        let scope = AsyncScope {
+            emitted: self.emitted.clone(),
+            handle: control.handle(),
+        };
+        self.running = Some(Box::pin(self.machine.run(scope, input)));
+        self.started = true;
+    }
+}
+
+impl<Machine> Component for AsyncNode<Machine>
+where
+    Machine: AsyncComponent,
+{
+    type Input = Machine::Input;
+    type Message = ();
+
+    fn construct(input: &Self::Input) -> Self {
+        Self {
+            machine: Machine::construct(input),
+            running: None,
+            started: false,
+            restart_pending: Cell::new(false),
+            emitted: Default::default(),
+            children: vec![],
+        }
+    }
+
+    fn step(&mut self, control: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        // First step, or a step with a new input that should_restart accepted
+        if !self.started || self.restart_pending.replace(false) {
+            self.start(control, input);
+        }
+
+        if let Some(running) = &mut self.running {
+            let polled = control.poll(running.as_mut());
+
+            if let Some(emitted) = self.emitted.borrow_mut().take() {
+                self.children = emitted;
+            }
+            if let Poll::Ready(children) = polled {
+                self.children = children;
+                self.running = None;
+            }
+        }
+
+        self.children.clone()
+    }
+
+    fn should_step(&self, old_input: &Self::Input, new_input: &Self::Input) -> bool {
+        let restart = self.machine.should_restart(old_input, new_input);
+        if restart {
+            self.restart_pending.set(true);
+        }
+        restart
+    }
+}
@@ -0,0 +1,83 @@
+use std::rc::Rc;
+
+use crate::{
+    key::Seed,
+    node::Component,
+    node_host::{
+        failure::{FailureAction, StepFailure},
+        NodeControl,
+    },
+};
+
+pub type Fallback = Rc<dyn Fn(&StepFailure) -> Vec<Seed>>;
+
+#[derive(Clone)]
+pub struct ErrorBoundaryInput {
+    pub children: Vec<Seed>,
+    pub fallback: Fallback,
+}
+
+impl ErrorBoundaryInput {
+    pub fn new<F>(children: Vec<Seed>, fallback: F) -> Self
+    where
+        F: Fn(&StepFailure) -> Vec<Seed> + 'static,
+    {
+        Self {
+            children,
+            fallback: Rc::new(fallback),
+        }
+    }
+}
+
+pub enum ErrorBoundaryMessage {
+    // Drop the caught failure and step the children again
+    Reset,
+}
+
+// Steps its children until one of their steps fails, then unmounts them and steps the
+// fallback built from the failure instead. Failures of the fallback are propagated.
+// Its input holds seeds and a closure, so it cannot be written to a snapshot.
+pub struct ErrorBoundary {
+    failure: Option<StepFailure>,
+}
+
+impl ErrorBoundary {
+    pub fn failure(&self) -> Option<&StepFailure> {
+        self.failure.as_ref()
+    }
+}
+
+impl Component for ErrorBoundary {
+    type Input = ErrorBoundaryInput;
+    type Message = ErrorBoundaryMessage;
+
+    fn construct(_: &Self::Input) -> Self {
+        Self { failure: None }
+    }
+
+    fn step(&mut self, _: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        match &self.failure {
+            Some(failure) => (input.fallback)(failure),
+            None => input.children.clone(),
+        }
+    }
+
+    fn on_message(&mut self, _: &mut NodeControl, message: Self::Message) {
+        match message {
+            ErrorBoundaryMessage::Reset => self.failure = None,
+        }
+    }
+
+    fn on_descendant_failure(
+        &mut self,
+        _: &mut NodeControl,
+        failure: &StepFailure,
+    ) -> FailureAction {
+        if self.failure.is_some() {
+            return FailureAction::Propagate;
+        }
+
+        self.failure = Some(failure.clone());
+        FailureAction::UnmountChild
+    }
+}
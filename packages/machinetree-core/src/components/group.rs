@@ -0,0 +1,28 @@
+use crate::{key::Seed, node::Component, node_host::NodeControl, persistence::PersistentComponent};
+
+// Has no children of its own, for nodes that only hold the children declared with
+// Seed::with_children or in a TreeSpec. Every ComponentRegistry knows it as "Group".
+pub struct Group;
+
+impl Component for Group {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Group
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+impl PersistentComponent for Group {
+    type State = ();
+
+    fn save_state(&self) -> Self::State {}
+
+    fn restore_state(_: &Self::Input, _: Self::State) -> Self {
+        Group
+    }
+}
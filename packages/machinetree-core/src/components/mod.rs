@@ -0,0 +1,7 @@
+mod error_boundary;
+mod group;
+mod supervisor;
+
+pub use error_boundary::{ErrorBoundary, ErrorBoundaryInput, ErrorBoundaryMessage};
+pub use group::Group;
+pub use supervisor::{RestartIntensity, RestartStrategy, Supervisor, SupervisorInput};
@@ -0,0 +1,148 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    key::Seed,
+    node::Component,
+    node_host::{
+        failure::{FailureAction, RestartScope, StepFailure},
+        NodeControl,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartStrategy {
+    // Restart the failing child only
+    OneForOne,
+    // Restart every child
+    OneForAll,
+    // Restart the failing child and the children after it
+    RestForOne,
+}
+
+impl From<RestartStrategy> for RestartScope {
+    fn from(strategy: RestartStrategy) -> Self {
+        match strategy {
+            RestartStrategy::OneForOne => RestartScope::Child,
+            RestartStrategy::OneForAll => RestartScope::AllChildren,
+            RestartStrategy::RestForOne => RestartScope::ChildAndFollowing,
+        }
+    }
+}
+
+// Restarts allowed within a sliding window before the supervisor gives up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartIntensity {
+    pub max_restarts: usize,
+    pub within: Duration,
+}
+
+impl Default for RestartIntensity {
+    fn default() -> Self {
+        Self {
+            max_restarts: 1,
+            within: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SupervisorInput {
+    pub children: Vec<Seed>,
+    pub strategy: RestartStrategy,
+    pub intensity: RestartIntensity,
+}
+
+impl SupervisorInput {
+    pub fn new(children: Vec<Seed>, strategy: RestartStrategy) -> Self {
+        Self {
+            children,
+            strategy,
+            intensity: Default::default(),
+        }
+    }
+
+    pub fn one_for_one(children: Vec<Seed>) -> Self {
+        Self::new(children, RestartStrategy::OneForOne)
+    }
+
+    pub fn one_for_all(children: Vec<Seed>) -> Self {
+        Self::new(children, RestartStrategy::OneForAll)
+    }
+
+    pub fn rest_for_one(children: Vec<Seed>) -> Self {
+        Self::new(children, RestartStrategy::RestForOne)
+    }
+
+    pub fn with_intensity(mut self, max_restarts: usize, within: Duration) -> Self {
+        self.intensity = RestartIntensity {
+            max_restarts,
+            within,
+        };
+        self
+    }
+}
+
+// Steps its children and restarts them from their seeds when a step fails below them.
+// Once the intensity is exceeded, failures are propagated to the ancestors instead.
+// Its input holds seeds, so it cannot be written to a snapshot.
+pub struct Supervisor {
+    strategy: RestartStrategy,
+    intensity: RestartIntensity,
+    restarts: VecDeque<Instant>,
+}
+
+impl Supervisor {
+    // Restarts within the current intensity window
+    pub fn recent_restarts(&self) -> usize {
+        self.restarts.len()
+    }
+
+    fn forget_restarts_before(&mut self, now: Instant) {
+        while let Some(restart) = self.restarts.front() {
+            if now.duration_since(*restart) < self.intensity.within {
+                break;
+            }
+            self.restarts.pop_front();
+        }
+    }
+}
+
+impl Component for Supervisor {
+    type Input = SupervisorInput;
+    type Message = ();
+
+    fn construct(input: &Self::Input) -> Self {
+        Self {
+            strategy: input.strategy,
+            intensity: input.intensity,
+            restarts: Default::default(),
+        }
+    }
+
+    fn step(&mut self, _: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        // Failures are handled outside of steps, keep the latest configuration around
+        self.strategy = input.strategy;
+        self.intensity = input.intensity;
+
+        input.children.clone()
+    }
+
+    fn on_descendant_failure(
+        &mut self,
+        control: &mut NodeControl,
+        _: &StepFailure,
+    ) -> FailureAction {
+        let now = control.now();
+        self.forget_restarts_before(now);
+
+        if self.restarts.len() >= self.intensity.max_restarts {
+            return FailureAction::Propagate;
+        }
+
+        self.restarts.push_back(now);
+        FailureAction::Restart(self.strategy.into())
+    }
+}
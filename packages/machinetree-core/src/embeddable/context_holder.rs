@@ -1,4 +1,7 @@
-use crate::node::Component;
+use crate::{
+    key::{Key, KeyWeak},
+    node::Component,
+};
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
@@ -8,30 +11,81 @@ use std::{
 #[derive(Default)]
 pub(crate) struct ContextHolder {
     pub type_map: HashMap<TypeIdOfContextContainer, Rc<dyn Any>>,
+    // Nodes that read a context from this holder, in the order of their first read
+    pub subscribers: HashMap<TypeIdOfContextContainer, Vec<KeyWeak>>,
 }
 
 impl ContextHolder {
-    pub(crate) fn get<'f, Container>(&'f self) -> Option<Rc<Container::Inner>>
+    pub(crate) fn get<Container>(&self) -> Option<Rc<Container::Inner>>
     where
         Container: ContextContainer + 'static,
     {
         let type_id_of_container = TypeId::of::<Container>();
         let inner_any = self.type_map.get(&type_id_of_container)?;
-        let inner_typed = Rc::downcast::<Container::Inner>(inner_any.clone()).unwrap();
-        Some(inner_typed)
+        Rc::downcast::<Container::Inner>(inner_any.clone()).ok()
     }
 
-    pub(crate) fn set<'f, Container>(
-        &mut self,
-        value: Container::Inner,
-    ) -> Option<Rc<Container::Inner>>
+    pub(crate) fn set<Container>(&mut self, value: Container::Inner) -> Option<Rc<Container::Inner>>
     where
         Container: ContextContainer + 'static,
     {
         let type_id_of_container = TypeId::of::<Container>();
         let inner_any = self.type_map.insert(type_id_of_container, Rc::new(value))?;
-        let inner_typed = Rc::downcast::<Container::Inner>(inner_any).unwrap();
-        Some(inner_typed)
+        Rc::downcast::<Container::Inner>(inner_any).ok()
+    }
+}
+
+impl ContextHolder {
+    pub(crate) fn subscribe<Container>(&mut self, reader: &Key)
+    where
+        Container: ContextContainer + 'static,
+    {
+        self.subscribe_type(TypeId::of::<Container>(), reader);
+    }
+
+    pub(crate) fn subscribe_type(&mut self, type_id: TypeId, reader: &Key) {
+        let readers = self.subscribers.entry(type_id).or_default();
+        let reader = KeyWeak::from(reader);
+        if !readers.iter().any(|subscriber| subscriber.ptr_eq(&reader)) {
+            readers.push(reader);
+        }
+    }
+
+    // Subscriptions are renewed by the next read, so taking them also clears them
+    pub(crate) fn take_subscribers<Container>(&mut self) -> Vec<Key>
+    where
+        Container: ContextContainer + 'static,
+    {
+        self.subscribers
+            .remove(&TypeId::of::<Container>())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|reader| Key::try_from(reader).ok())
+            .collect()
+    }
+
+    // Take the readers matching `predicate`, the others stay subscribed
+    pub(crate) fn take_subscribers_where<Container>(
+        &mut self,
+        predicate: impl Fn(&Key) -> bool,
+    ) -> Vec<Key>
+    where
+        Container: ContextContainer + 'static,
+    {
+        let readers = match self.subscribers.get_mut(&TypeId::of::<Container>()) {
+            Some(readers) => readers,
+            None => return vec![],
+        };
+        let mut taken = vec![];
+        readers.retain(|reader| match Key::try_from(reader) {
+            Ok(reader) if predicate(&reader) => {
+                taken.push(reader);
+                false
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        });
+        taken
     }
 }
 
@@ -0,0 +1,120 @@
+use std::any::Any;
+
+pub type EffectCleanup = Box<dyn FnOnce()>;
+pub(crate) type Effect = Box<dyn FnOnce() -> Option<EffectCleanup>>;
+type DepsEq = fn(&dyn Any, &dyn Any) -> bool;
+
+// An effect requested through NodeControl::use_effect during a step
+pub(crate) struct EffectRegistration {
+    deps: Box<dyn Any>,
+    deps_eq: DepsEq,
+    effect: Effect,
+}
+
+impl EffectRegistration {
+    pub(crate) fn new<Deps>(deps: Deps, effect: Effect) -> Self
+    where
+        Deps: PartialEq + 'static,
+    {
+        Self {
+            deps: Box::new(deps),
+            deps_eq: deps_eq::<Deps>,
+            effect,
+        }
+    }
+}
+
+fn deps_eq<Deps>(a: &dyn Any, b: &dyn Any) -> bool
+where
+    Deps: PartialEq + 'static,
+{
+    match (a.downcast_ref::<Deps>(), b.downcast_ref::<Deps>()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+struct EffectSlot {
+    deps: Box<dyn Any>,
+    cleanup: Option<EffectCleanup>,
+}
+
+// Effects are identified by the order in which a step registers them
+#[derive(Default)]
+pub(crate) struct EffectManager {
+    slots: Vec<EffectSlot>,
+    pending_effects: Vec<(usize, Effect)>,
+    pending_cleanups: Vec<EffectCleanup>,
+}
+
+impl EffectManager {
+    // Compare the registrations of the latest step against the previous ones,
+    // queueing the effects whose dependencies changed
+    pub(crate) fn merge(&mut self, registrations: Vec<EffectRegistration>) {
+        // A step that registers fewer effects than before cleans up the rest
+        if registrations.len() < self.slots.len() {
+            self.slots
+                .drain(registrations.len()..)
+                .filter_map(|slot| slot.cleanup)
+                .for_each(|cleanup| self.pending_cleanups.push(cleanup));
+        }
+
+        self.pending_effects.clear();
+
+        registrations
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, registration)| {
+                let EffectRegistration {
+                    deps,
+                    deps_eq,
+                    effect,
+                } = registration;
+
+                match self.slots.get_mut(index) {
+                    Some(slot) if deps_eq(slot.deps.as_ref(), deps.as_ref()) => {}
+                    Some(slot) => {
+                        slot.deps = deps;
+                        self.pending_effects.push((index, effect));
+                    }
+                    None => {
+                        self.slots.push(EffectSlot {
+                            deps,
+                            cleanup: None,
+                        });
+                        self.pending_effects.push((index, effect));
+                    }
+                }
+            });
+    }
+
+    // Run queued cleanups and effects, called once the render pass is committed
+    pub(crate) fn flush(&mut self) {
+        self.pending_cleanups
+            .drain(..)
+            .for_each(|cleanup| cleanup());
+
+        let pending_effects = std::mem::take(&mut self.pending_effects);
+        pending_effects.into_iter().for_each(|(index, effect)| {
+            if let Some(slot) = self.slots.get_mut(index) {
+                if let Some(cleanup) = slot.cleanup.take() {
+                    cleanup();
+                }
+                slot.cleanup = effect();
+            }
+        });
+    }
+
+    // Run every outstanding cleanup, last registered first, called on unmount
+    pub(crate) fn cleanup_all(&mut self) {
+        self.pending_effects.clear();
+        self.pending_cleanups
+            .drain(..)
+            .for_each(|cleanup| cleanup());
+        self.slots
+            .drain(..)
+            .rev()
+            .filter_map(|slot| slot.cleanup)
+            .for_each(|cleanup| cleanup());
+    }
+}
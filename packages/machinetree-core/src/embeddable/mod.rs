@@ -1,4 +1,4 @@
-// pub(crate) mod effect_manager;
 // pub(crate) mod input_manager;
-// pub(crate) mod state_manager;
-pub(crate) mod context_holder;
+pub mod context_holder;
+pub mod effect_manager;
+pub mod state_manager;
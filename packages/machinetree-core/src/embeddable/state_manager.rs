@@ -0,0 +1,90 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::node::{NodeHandle, NodeHandleError};
+
+// State slots are identified by the order in which a step requests them
+#[derive(Default)]
+pub(crate) struct StateManager {
+    slots: Vec<Box<dyn Any>>,
+}
+
+impl StateManager {
+    pub(crate) fn slot<T, Init>(&mut self, index: usize, init: Init) -> Arc<Mutex<T>>
+    where
+        T: Send + 'static,
+        Init: FnOnce() -> T,
+    {
+        if let Some(existing) = self
+            .slots
+            .get(index)
+            .and_then(|slot| slot.downcast_ref::<Arc<Mutex<T>>>())
+        {
+            return existing.clone();
+        }
+
+        let value = Arc::new(Mutex::new(init()));
+        let slot: Box<dyn Any> = Box::new(value.clone());
+        match self.slots.get_mut(index) {
+            // A different type was stored at this index, the step changed its hook order
+            Some(existing) => *existing = slot,
+            None => self.slots.push(slot),
+        }
+
+        value
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+// Handle to a piece of node state created through NodeControl::use_state.
+// Updating it from any thread stores the value and enqueues a rerender of the node.
+pub struct StateHandle<T> {
+    pub(crate) value: Arc<Mutex<T>>,
+    pub(crate) handle: NodeHandle,
+}
+
+impl<T> Clone for StateHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<T> StateHandle<T> {
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.value.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.lock().clone()
+    }
+
+    pub fn with<R>(&self, read: impl FnOnce(&T) -> R) -> R {
+        read(&self.lock())
+    }
+
+    // The value is stored even if the node can no longer be rerendered
+    pub fn set(&self, value: T) -> Result<(), NodeHandleError> {
+        *self.lock() = value;
+        self.handle.rerender()
+    }
+
+    pub fn update(&self, update: impl FnOnce(&mut T)) -> Result<(), NodeHandleError> {
+        update(&mut self.lock());
+        self.handle.rerender()
+    }
+
+    pub fn handle(&self) -> &NodeHandle {
+        &self.handle
+    }
+}
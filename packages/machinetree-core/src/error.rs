@@ -0,0 +1,153 @@
+use std::fmt::Display;
+
+use crate::{node::NodeHandleError, node_host::failure::StepFailure};
+
+#[derive(Clone, Debug)]
+pub enum MachineTreeError {
+    // A node's key was poisoned by a panic while it was locked
+    PoisonedKey,
+    // The node is no longer in the tree
+    MissingNode,
+    // A type-erased value does not have the type its component expects
+    TypeMismatch,
+    DeadHandle(NodeHandleError),
+    // A step failed and no ancestor handled the failure
+    StepFailure(StepFailure),
+    // Time can only be advanced on a host built with a ManualClock
+    ClockNotManual,
+    // No component is registered under the name
+    UnknownComponent {
+        name: String,
+    },
+    // The input does not deserialize to the input of the component
+    InvalidInput {
+        component: String,
+        reason: String,
+    },
+    // A TreeSpec does not describe a tree of registered components, `path` points at the
+    // offending field of the document
+    InvalidSpec {
+        path: String,
+        reason: String,
+    },
+    // A node of a snapshot has no persistent component registered for it
+    UnregisteredComponent {
+        path: String,
+    },
+    // A node holds what a snapshot cannot keep, like state from NodeControl::use_state
+    NotPersistent {
+        path: String,
+        reason: String,
+    },
+    // A context of a snapshot has no registered container
+    UnregisteredContext {
+        name: String,
+        path: String,
+    },
+    Serialization(String),
+    // The snapshot does not describe a tree
+    InvalidSnapshot(String),
+    // No chain of registered migrations leads from the saved version of the node's state
+    NoMigrationPath {
+        path: String,
+        from: u32,
+        to: u32,
+    },
+    MigrationFailed {
+        path: String,
+        from: u32,
+        to: u32,
+        reason: String,
+    },
+    // The saved input, state or context value of the node does not deserialize
+    RestoreFailed {
+        path: String,
+        reason: String,
+    },
+    // Reading or writing a FileStore failed
+    Storage(String),
+}
+
+impl Display for MachineTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MachineTreeError::PoisonedKey => f.write_str("node key is poisoned"),
+            MachineTreeError::MissingNode => f.write_str("node is missing from the tree"),
+            MachineTreeError::TypeMismatch => f.write_str("value has an unexpected type"),
+            MachineTreeError::DeadHandle(error) => write!(f, "dead handle: {}", error),
+            MachineTreeError::StepFailure(failure) => write!(f, "step failed: {}", failure),
+            MachineTreeError::ClockNotManual => f.write_str("host clock is not manual"),
+            MachineTreeError::UnknownComponent { name } => {
+                write!(f, "no component registered as {:?}", name)
+            }
+            MachineTreeError::InvalidInput { component, reason } => {
+                write!(f, "invalid input for {:?}: {}", component, reason)
+            }
+            MachineTreeError::InvalidSpec { path, reason } => {
+                write!(f, "invalid spec at {}: {}", path, reason)
+            }
+            MachineTreeError::UnregisteredComponent { path } => {
+                write!(f, "no persistent component registered for {}", path)
+            }
+            MachineTreeError::NotPersistent { path, reason } => {
+                write!(f, "{} cannot be persisted: {}", path, reason)
+            }
+            MachineTreeError::UnregisteredContext { name, path } => {
+                write!(f, "no context registered as {:?} at {}", name, path)
+            }
+            MachineTreeError::Serialization(error) => write!(f, "serialization failed: {}", error),
+            MachineTreeError::InvalidSnapshot(reason) => write!(f, "invalid snapshot: {}", reason),
+            MachineTreeError::NoMigrationPath { path, from, to } => write!(
+                f,
+                "no migration path for {} from version {} to {}",
+                path, from, to
+            ),
+            MachineTreeError::MigrationFailed {
+                path,
+                from,
+                to,
+                reason,
+            } => write!(
+                f,
+                "migration of {} from version {} to {} failed: {}",
+                path, from, to, reason
+            ),
+            MachineTreeError::RestoreFailed { path, reason } => {
+                write!(f, "restoring {} failed: {}", path, reason)
+            }
+            MachineTreeError::Storage(error) => write!(f, "storage failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for MachineTreeError {}
+
+impl From<NodeHandleError> for MachineTreeError {
+    fn from(error: NodeHandleError) -> Self {
+        MachineTreeError::DeadHandle(error)
+    }
+}
+
+impl From<StepFailure> for MachineTreeError {
+    fn from(failure: StepFailure) -> Self {
+        MachineTreeError::StepFailure(failure)
+    }
+}
+
+impl From<serde_json::Error> for MachineTreeError {
+    fn from(error: serde_json::Error) -> Self {
+        MachineTreeError::Serialization(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for MachineTreeError {
+    fn from(error: std::io::Error) -> Self {
+        MachineTreeError::Storage(error.to_string())
+    }
+}
+
+impl From<ron::error::SpannedError> for MachineTreeError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        MachineTreeError::Serialization(error.to_string())
+    }
+}
@@ -1,17 +1,29 @@
 use std::{
     any::{Any, TypeId},
     cell::RefCell,
+    fmt::Debug,
     hash::Hash,
     rc::Rc,
     sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
 };
 
-use crate::{node::SelfRender, node_host::NodeControl};
+use crate::{
+    error::MachineTreeError,
+    node::{AbstractComponent, SeedInput, SelfRender},
+};
 
 pub struct SeedData {
-    pub(crate) input: AnyBox,
-    pub(crate) inherit_input_fn_box: CloneInputBox,
-    pub(crate) step_fn: BoxedAbsStep,
+    // Components are constructed from it when the seed sprouts, so that a seed can be cloned
+    pub(crate) input: SeedInputBox,
+    pub(crate) declared_children: Vec<Seed>,
+    // Set by ComponentRegistry::seed, so that declared children can be written to snapshots
+    pub(crate) named: Option<NamedInput>,
+}
+
+#[derive(Clone)]
+pub(crate) struct NamedInput {
+    pub(crate) component: String,
+    pub(crate) input: serde_json::Value,
 }
 
 pub struct Seed {
@@ -22,34 +34,72 @@ pub struct Seed {
 }
 
 impl Seed {
+    // Children declared along the seed, added after the ones each step returns
+    pub fn with_children(mut self, children: Vec<Seed>) -> Seed {
+        self.data.declared_children = children;
+        self
+    }
+
     pub(crate) fn clone_input(&self) -> AnyBox {
-        (self.data.inherit_input_fn_box)(&self.data.input)
+        self.data.input.clone_input()
     }
 
     pub(crate) fn sprout(self) -> (RawKey, RawData) {
         let Seed {
             key,
-            data: SeedData { input, step_fn, .. },
+            data:
+                SeedData {
+                    input,
+                    declared_children,
+                    ..
+                },
         } = self;
+        let component: BoxedAbsComponent = Box::new(RefCell::new(input.construct()));
         (
             key,
             RawData {
-                input: input,
-                step_fn: step_fn,
+                input: input.into_input(),
+                previous_input: None,
+                declared_children,
+                component,
+                mounted: false,
             },
         )
     }
 }
 
+impl Clone for Seed {
+    fn clone(&self) -> Self {
+        Seed {
+            key: RawKey {
+                type_id: self.key.type_id,
+                key: self.key.key.clone(),
+                self_render: Default::default(),
+                detached: false,
+            },
+            data: SeedData {
+                input: self.data.input.clone_seed_input(),
+                declared_children: self.data.declared_children.clone(),
+                named: self.data.named.clone(),
+            },
+        }
+    }
+}
+
 // NodeData is !Sync + !Send
 pub struct RawData {
     pub(crate) input: AnyBox,
-    pub(crate) step_fn: BoxedAbsStep,
+    // Input seen by the last step, kept when the parent replaces `input` and cleared by the next step
+    pub(crate) previous_input: Option<AnyBox>,
+    // Seeds of Seed::with_children, replaced along the input
+    pub(crate) declared_children: Vec<Seed>,
+    pub(crate) component: BoxedAbsComponent,
+    pub(crate) mounted: bool,
 }
 
-impl Into<DataRc> for RawData {
-    fn into(self) -> DataRc {
-        Rc::new(RefCell::new(self))
+impl From<RawData> for DataRc {
+    fn from(val: RawData) -> Self {
+        Rc::new(RefCell::new(val))
     }
 }
 
@@ -58,6 +108,8 @@ pub struct RawKey {
     pub(crate) type_id: TypeId,
     pub(crate) key: Option<String>,
     pub(crate) self_render: SelfRender,
+    // Set once the node is removed from the lake
+    pub(crate) detached: bool,
 }
 
 impl Hash for RawKey {
@@ -98,6 +150,12 @@ impl PartialEq for Key {
 
 impl Eq for Key {}
 
+impl Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.debug_attempt_get_name())
+    }
+}
+
 impl From<KeyArc> for Key {
     fn from(node_rc: KeyArc) -> Self {
         Key(node_rc)
@@ -111,16 +169,19 @@ impl From<&KeyArc> for Key {
 }
 
 impl TryFrom<&KeyWeak> for Key {
-    type Error = ();
+    type Error = MachineTreeError;
 
     fn try_from(value: &KeyWeak) -> Result<Self, Self::Error> {
-        value.upgrade().map(|x| Key::from(x)).ok_or(())
+        value
+            .upgrade()
+            .map(Key::from)
+            .ok_or(MachineTreeError::MissingNode)
     }
 }
 
-impl Into<KeyWeak> for &Key {
-    fn into(self) -> KeyWeak {
-        Arc::downgrade(&self.0)
+impl From<&Key> for KeyWeak {
+    fn from(val: &Key) -> Self {
+        Arc::downgrade(&val.0)
     }
 }
 
@@ -146,24 +207,22 @@ impl Key {
                 &node_key_raw.get_type_id_string(),
                 &node_key_raw.get_key_string()
             ),
-            Err(_) => format!("unidentifiable"),
+            Err(_) => "unidentifiable".to_string(),
         }
     }
 }
 
-impl Into<KeyArc> for RawKey {
-    fn into(self) -> KeyArc {
-        Arc::new(Mutex::new(self))
+impl From<RawKey> for KeyArc {
+    fn from(val: RawKey) -> Self {
+        Arc::new(Mutex::new(val))
     }
 }
 
-// TODO: make StepFn more accomodating toward Self Struct, and not relying on FnMut
 pub(crate) type AnyBox = Box<dyn Any>;
-pub(crate) type CloneInput = fn(&AnyBox) -> AnyBox;
-pub(crate) type CloneInputBox = Box<CloneInput>;
-pub(crate) type Step<Input> = Box<dyn FnMut(&mut NodeControl, &Input) -> Vec<Seed>>;
-pub(crate) type AbsStep = Step<AnyBox>;
-pub(crate) type BoxedAbsStep = Box<RefCell<AbsStep>>;
+pub(crate) type AnyMessage = Box<dyn Any + Send>;
+pub(crate) type SeedInputBox = Box<dyn SeedInput>;
+pub(crate) type AbsComponent = Box<dyn AbstractComponent>;
+pub(crate) type BoxedAbsComponent = Box<RefCell<AbsComponent>>;
 pub(crate) type KeyMutex = Mutex<RawKey>;
 pub(crate) type KeyArc = Arc<KeyMutex>;
 pub(crate) type KeyWeak = Weak<KeyMutex>;
@@ -1,4 +1,9 @@
+pub mod async_component;
+pub mod components;
 pub mod embeddable;
+pub mod error;
 pub mod key;
 pub mod node;
 pub mod node_host;
+pub mod persistence;
+pub mod spec;
@@ -1,126 +1,454 @@
+use crate::error::MachineTreeError;
+use crate::key::AbsComponent;
 use crate::key::AnyBox;
-use crate::key::BoxedAbsStep;
-use crate::key::CloneInputBox;
+use crate::key::AnyMessage;
 use crate::key::Key;
+use crate::key::KeyWeak;
 use crate::key::RawKey;
 use crate::key::Seed;
 use crate::key::SeedData;
+use crate::key::SeedInputBox;
+use crate::node_host::failure::{FailureAction, StepError, StepFailure};
 use crate::node_host::NodeControl;
-use std::{any::TypeId, cell::RefCell};
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Wake, Waker},
+};
 
-mod component_utils {
+pub(crate) mod component_utils {
     use crate::node_host::NodeControl;
 
-    use super::Component;
-    use crate::key::{AbsStep, AnyBox};
+    use std::any::Any;
 
-    pub fn downcast_as_input_ref<'a, Input>(input: &'a AnyBox) -> &'a Input
+    use super::{AbstractComponent, Component, SeedInput};
+    use crate::{
+        error::MachineTreeError,
+        key::{AbsComponent, AnyBox, AnyMessage, Seed, SeedInputBox},
+        node_host::failure::{FailureAction, StepError, StepFailure},
+    };
+
+    pub fn downcast_as_input_ref<Input>(input: &AnyBox) -> Result<&Input, MachineTreeError>
     where
         Input: Sized + Clone + 'static,
     {
-        input.downcast_ref::<Input>().unwrap()
+        input
+            .downcast_ref::<Input>()
+            .ok_or(MachineTreeError::TypeMismatch)
     }
 
-    pub fn clone_input_box<Input>(abstract_input_box: &AnyBox) -> AnyBox
+    // Input of a seed built by Component::seed, which knows the component it belongs to
+    pub struct TypedSeedInput<Machine: Component>(pub Machine::Input);
+
+    impl<Machine> SeedInput for TypedSeedInput<Machine>
     where
-        Input: Sized + Clone + 'static,
+        Machine: Component,
     {
-        Box::new(downcast_as_input_ref::<Input>(&abstract_input_box).clone())
+        fn clone_seed_input(&self) -> SeedInputBox {
+            Box::new(TypedSeedInput::<Machine>(self.0.clone()))
+        }
+
+        fn clone_input(&self) -> AnyBox {
+            Box::new(self.0.clone())
+        }
+
+        fn construct(&self) -> AbsComponent {
+            hold(Machine::construct(&self.0))
+        }
+
+        fn into_input(self: Box<Self>) -> AnyBox {
+            Box::new(self.0)
+        }
     }
 
-    pub fn generate_abstract_step_fn<Machine, Input>(input: &Input) -> AbsStep
+    pub struct ComponentHolder<Machine>(Machine);
+
+    impl<Machine> AbstractComponent for ComponentHolder<Machine>
     where
-        Input: Sized + Clone + 'static,
-        Machine: Sized + Component<Input = Input> + 'static,
+        Machine: Component,
+    {
+        fn try_step(
+            &mut self,
+            control: &mut NodeControl,
+            input: &AnyBox,
+        ) -> Result<Vec<Seed>, StepError> {
+            let input_ref = downcast_as_input_ref::<Machine::Input>(input)?;
+            self.0.try_step(control, input_ref)
+        }
+
+        fn should_step(&self, old_input: &AnyBox, new_input: &AnyBox) -> bool {
+            match (
+                downcast_as_input_ref::<Machine::Input>(old_input),
+                downcast_as_input_ref::<Machine::Input>(new_input),
+            ) {
+                (Ok(old_input), Ok(new_input)) => self.0.should_step(old_input, new_input),
+                _ => true,
+            }
+        }
+
+        fn on_message(&mut self, control: &mut NodeControl, message: AnyMessage) {
+            // NodeControl::mailbox only builds mailboxes for the node's own component
+            if let Ok(message) = message.downcast::<Machine::Message>() {
+                self.0.on_message(control, *message)
+            }
+        }
+
+        fn on_mount(&mut self, control: &mut NodeControl) {
+            self.0.on_mount(control)
+        }
+
+        fn on_unmount(&mut self, control: &mut NodeControl) {
+            self.0.on_unmount(control)
+        }
+
+        fn on_descendant_failure(
+            &mut self,
+            control: &mut NodeControl,
+            failure: &StepFailure,
+        ) -> FailureAction {
+            self.0.on_descendant_failure(control, failure)
+        }
+
+        fn component_any(&self) -> &dyn Any {
+            &self.0
+        }
+
+        fn type_name(&self) -> &'static str {
+            std::any::type_name::<Machine>()
+        }
+    }
+
+    pub fn hold<Machine>(machine: Machine) -> AbsComponent
+    where
+        Machine: Component,
     {
-        let mut self_state = Machine::construct(input);
-        Box::new(move |control: &mut NodeControl, input: &AnyBox| {
-            let input_ref = downcast_as_input_ref::<Input>(input);
-            self_state.step(control, input_ref)
-        })
+        Box::new(ComponentHolder(machine))
     }
 }
 
+// Type-erased input of a Seed, constructing the component it was seeded for
+pub(crate) trait SeedInput {
+    fn clone_seed_input(&self) -> SeedInputBox;
+
+    fn clone_input(&self) -> AnyBox;
+
+    fn construct(&self) -> AbsComponent;
+
+    fn into_input(self: Box<Self>) -> AnyBox;
+}
+
+// Type-erased view of a Component, stored in RawData
+pub(crate) trait AbstractComponent {
+    fn try_step(
+        &mut self,
+        control: &mut NodeControl,
+        input: &AnyBox,
+    ) -> Result<Vec<Seed>, StepError>;
+
+    fn should_step(&self, old_input: &AnyBox, new_input: &AnyBox) -> bool;
+
+    fn on_message(&mut self, control: &mut NodeControl, message: AnyMessage);
+
+    fn on_mount(&mut self, control: &mut NodeControl);
+
+    fn on_unmount(&mut self, control: &mut NodeControl);
+
+    fn on_descendant_failure(
+        &mut self,
+        control: &mut NodeControl,
+        failure: &StepFailure,
+    ) -> FailureAction;
+
+    // The Component itself, for the persistence registry
+    fn component_any(&self) -> &dyn Any;
+
+    fn type_name(&self) -> &'static str;
+}
+
 pub trait Component
 where
     Self: Sized + 'static,
 {
     type Input: Sized + Clone + 'static;
 
+    // Messages accepted through a Mailbox, use `()` for components without one.
+    // Associated types cannot have defaults on stable Rust, so implementations written before
+    // mailboxes existed have to add `type Message = ();`.
+    type Message: Send + 'static;
+
     fn construct(input: &Self::Input) -> Self
     where
         Self: Sized + 'static;
 
     fn seed(input: Self::Input, key: String) -> Seed {
         let type_id = TypeId::of::<Self>();
-        let step_fn: BoxedAbsStep =
-            Box::new(RefCell::new(component_utils::generate_abstract_step_fn::<
-                Self,
-                Self::Input,
-            >(&input)));
-        let input: AnyBox = Box::new(input);
-        let inherit_input_fn_box: CloneInputBox =
-            Box::new(component_utils::clone_input_box::<Self::Input>);
+        let input: SeedInputBox = Box::new(component_utils::TypedSeedInput::<Self>(input));
         let self_render_signaler = Default::default();
 
         Seed {
             key: RawKey {
                 type_id,
-                key,
+                key: Some(key),
                 self_render: self_render_signaler,
+                detached: false,
             },
             data: SeedData {
                 input,
-                inherit_input_fn_box,
-                step_fn,
+                declared_children: vec![],
+                named: None,
             },
         }
     }
 
     fn step(&mut self, control: &mut NodeControl, input: &Self::Input) -> Vec<Seed>;
+
+    // Fallible variant of `step`, the one called by the host.
+    // An Err fails the step the same way a panic does: the step's children and effects are
+    // discarded and the failure is handed to the ancestors through on_descendant_failure.
+    fn try_step(
+        &mut self,
+        control: &mut NodeControl,
+        input: &Self::Input,
+    ) -> Result<Vec<Seed>, StepError> {
+        Ok(self.step(control, input))
+    }
+
+    // Called when the parent rerenders with a new input for this node.
+    // Returning false keeps the node and its children as they are instead of stepping it,
+    // `input_changed` implements this for inputs that are PartialEq.
+    fn should_step(&self, _old_input: &Self::Input, _new_input: &Self::Input) -> bool {
+        true
+    }
+
+    // Called for each message received through the node's Mailbox, right before its next step
+    fn on_message(&mut self, _control: &mut NodeControl, _message: Self::Message) {}
+
+    // Called once after the render pass that first stepped this node.
+    // Within a pass, children are mounted before their parents.
+    fn on_mount(&mut self, _control: &mut NodeControl) {}
+
+    // Called once when the node is about to be removed from the tree.
+    // Children are unmounted before their parents.
+    fn on_unmount(&mut self, _control: &mut NodeControl) {}
+
+    // Called when the step of a node below this one failed, closest ancestor first.
+    // Returning FailureAction::UnmountChild makes this node an error boundary for the failure:
+    // the child holding the failing node is unmounted with its subtree and this node is stepped
+    // again. See components::ErrorBoundary.
+    fn on_descendant_failure(
+        &mut self,
+        _control: &mut NodeControl,
+        _failure: &StepFailure,
+    ) -> FailureAction {
+        FailureAction::Propagate
+    }
+}
+
+// Memoization helper for Component::should_step
+pub fn input_changed<Input>(old_input: &Input, new_input: &Input) -> bool
+where
+    Input: PartialEq,
+{
+    old_input != new_input
 }
 
 #[derive(Clone)]
 pub struct SelfRenderSet {
-    pub(crate) sender: crossbeam::channel::Sender<Key>,
-    pub(crate) self_key: Key,
+    pub(crate) sender: ExternalSender,
+    // Weak, as the SelfRender is itself stored inside the key
+    pub(crate) self_key: KeyWeak,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum SelfRender {
+    #[default]
     Unset,
     Set(SelfRenderSet),
 }
 
-impl Default for SelfRender {
-    fn default() -> Self {
-        SelfRender::Unset
-    }
-}
-
 impl SelfRender {
-    pub(crate) fn set_self(&mut self, node_key: &Key, sender: &crossbeam::channel::Sender<Key>) {
+    pub(crate) fn set_self(&mut self, node_key: &Key, sender: &ExternalSender) {
         if let SelfRender::Set(_) = &self {
             return;
         }
 
         *self = SelfRender::Set(SelfRenderSet {
             sender: sender.clone(),
-            self_key: node_key.clone(),
+            self_key: node_key.into(),
         });
     }
 
-    pub fn rerender(&self) -> Result<(), ()> {
+    pub fn rerender(&self) -> Result<(), MachineTreeError> {
         match self {
-            SelfRender::Set(signaler) => signaler
-                .sender
-                .send(signaler.self_key.clone())
-                .map_err(|_| {}),
+            SelfRender::Set(signaler) => {
+                let self_key = Key::try_from(&signaler.self_key)
+                    .map_err(|_| MachineTreeError::DeadHandle(NodeHandleError::Unmounted))?;
+                signaler
+                    .sender
+                    .send(ExternalWorkItem::Render(self_key))
+                    .map_err(|_| MachineTreeError::DeadHandle(NodeHandleError::Disconnected))
+            }
             _ => Ok(()),
         }
     }
 }
 
-pub(crate) enum WorkItem {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeHandleError {
+    // The node has been removed from the tree
+    Unmounted,
+    // The NodeHost owning the node no longer exists, or never attached the node
+    Disconnected,
+}
+
+impl std::fmt::Display for NodeHandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeHandleError::Unmounted => f.write_str("node is unmounted"),
+            NodeHandleError::Disconnected => f.write_str("node host is disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for NodeHandleError {}
+
+// Thread-safe reference to a node, obtained through NodeControl::handle.
+// It does not keep the node alive.
+#[derive(Clone)]
+pub struct NodeHandle {
+    pub(crate) key: KeyWeak,
+    pub(crate) sender: Option<ExternalSender>,
+}
+
+impl NodeHandle {
+    pub(crate) fn new(node_key: &Key) -> Self {
+        let sender = node_key
+            .lock()
+            .ok()
+            .and_then(|raw_key| match &raw_key.self_render {
+                SelfRender::Set(signaler) => Some(signaler.sender.clone()),
+                SelfRender::Unset => None,
+            });
+
+        Self {
+            key: node_key.into(),
+            sender,
+        }
+    }
+
+    fn upgrade(&self) -> Result<Key, NodeHandleError> {
+        let node_key = Key::try_from(&self.key).map_err(|_| NodeHandleError::Unmounted)?;
+        match node_key.lock() {
+            Ok(raw_key) if !raw_key.detached => {}
+            _ => return Err(NodeHandleError::Unmounted),
+        }
+        Ok(node_key)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.upgrade().is_ok()
+    }
+
+    fn send(&self, work: impl FnOnce(Key) -> ExternalWorkItem) -> Result<(), NodeHandleError> {
+        let node_key = self.upgrade()?;
+        let sender = self.sender.as_ref().ok_or(NodeHandleError::Disconnected)?;
+        sender
+            .send(work(node_key))
+            .map_err(|_| NodeHandleError::Disconnected)
+    }
+
+    // Enqueue a rerender of the node, picked up by the host on its next NodeHost::poll_work
+    pub fn rerender(&self) -> Result<(), NodeHandleError> {
+        self.send(ExternalWorkItem::Render)
+    }
+
+    // Waker rerendering the node, so that a future polled in its step gets it stepped again
+    pub fn waker(&self) -> Waker {
+        Waker::from(Arc::new(NodeWaker(self.clone())))
+    }
+}
+
+struct NodeWaker(NodeHandle);
+
+impl Wake for NodeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    // Waking an unmounted node does nothing
+    fn wake_by_ref(self: &Arc<Self>) {
+        let _ = self.0.rerender();
+    }
+}
+
+// Typed sender of messages to a node, obtained through NodeControl::mailbox
+pub struct Mailbox<Message> {
+    pub(crate) handle: NodeHandle,
+    pub(crate) message_type: PhantomData<fn(Message)>,
+}
+
+impl<Message> Clone for Mailbox<Message> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            message_type: PhantomData,
+        }
+    }
+}
+
+impl<Message> Mailbox<Message>
+where
+    Message: Send + 'static,
+{
+    // Queue the message for the node and enqueue its rerender.
+    // The host delivers it through Component::on_message before the node's next step.
+    pub fn send(&self, message: Message) -> Result<(), NodeHandleError> {
+        self.handle
+            .send(|node_key| ExternalWorkItem::Message(node_key, Box::new(message)))
+    }
+
+    pub fn handle(&self) -> &NodeHandle {
+        &self.handle
+    }
+}
+
+pub enum WorkItem {
     Render(Key),
 }
+
+// Work sent to the host from outside of a render pass
+pub(crate) enum ExternalWorkItem {
+    Render(Key),
+    Message(Key, AnyMessage),
+    // Sent through a ShutdownHandle to stop NodeHost::run
+    Shutdown,
+}
+
+// Waker of the task awaiting the host's external work, see AsyncNodeHost
+pub(crate) type ExternalWaker = Arc<Mutex<Option<Waker>>>;
+
+// Sender of the host's external work queue, waking the task awaiting it on every send
+#[derive(Clone)]
+pub(crate) struct ExternalSender {
+    pub(crate) sender: crossbeam::channel::Sender<ExternalWorkItem>,
+    pub(crate) waker: ExternalWaker,
+}
+
+impl ExternalSender {
+    pub(crate) fn send(
+        &self,
+        work: ExternalWorkItem,
+    ) -> Result<(), crossbeam::channel::SendError<ExternalWorkItem>> {
+        self.sender.send(work)?;
+        if let Some(waker) = self
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            waker.wake_by_ref();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Instant,
+};
+
+use futures_core::Stream;
+
+use super::{NodeHost, RenderReport};
+
+// Drives a NodeHost from an async executor.
+// As a Stream it yields the report of every render, and sleeps while there is no work until a
+// node is rerendered from outside, a message is sent, or a ShutdownHandle ends the stream.
+// Timers of the tree are awaited on a thread of its own, the host not depending on any executor.
+pub struct AsyncNodeHost {
+    host: NodeHost,
+    timer_thread: TimerThread,
+}
+
+impl AsyncNodeHost {
+    pub fn new(host: NodeHost) -> Self {
+        Self {
+            host,
+            timer_thread: TimerThread::default(),
+        }
+    }
+
+    pub fn host(&self) -> &NodeHost {
+        &self.host
+    }
+
+    pub fn host_mut(&mut self) -> &mut NodeHost {
+        &mut self.host
+    }
+
+    pub fn into_inner(self) -> NodeHost {
+        self.host.external_render_work_queue.register_waker(None);
+        self.host
+    }
+
+    // Resolves with the report of the next render, or None once shut down
+    pub fn next_report(&mut self) -> NextReport<'_> {
+        NextReport { async_host: self }
+    }
+
+    fn poll_report(&mut self, cx: &mut Context<'_>) -> Poll<Option<RenderReport>> {
+        let host = &mut self.host;
+        host.poll_work();
+
+        if host.shutdown_requested {
+            host.shutdown_requested = false;
+            host.external_render_work_queue.register_waker(None);
+            return Poll::Ready(None);
+        }
+
+        if !host.scheduler.is_empty() {
+            return Poll::Ready(Some(host.render()));
+        }
+
+        host.external_render_work_queue
+            .register_waker(Some(cx.waker()));
+
+        // Work sent before the waker was registered woke nobody
+        if !host.external_render_work_queue.receiver.is_empty() || host.has_deferred_work() {
+            cx.waker().wake_by_ref();
+        }
+
+        let deadline = host.timer_wake_deadline();
+        self.timer_thread.arm(deadline, cx.waker());
+
+        Poll::Pending
+    }
+}
+
+impl Stream for AsyncNodeHost {
+    type Item = RenderReport;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_report(cx)
+    }
+}
+
+pub struct NextReport<'a> {
+    async_host: &'a mut AsyncNodeHost,
+}
+
+impl Future for NextReport<'_> {
+    type Output = Option<RenderReport>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().async_host.poll_report(cx)
+    }
+}
+
+impl From<NodeHost> for AsyncNodeHost {
+    fn from(host: NodeHost) -> Self {
+        Self::new(host)
+    }
+}
+
+#[derive(Default)]
+struct TimerState {
+    deadline: Option<Instant>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+// A single thread, started with the first deadline, waking the task once the deadline it was
+// last armed with is reached. Rearming replaces the deadline, so cleared timers wake nobody.
+#[derive(Default)]
+struct TimerThread {
+    shared: Arc<(Mutex<TimerState>, Condvar)>,
+    started: bool,
+}
+
+impl TimerThread {
+    fn arm(&mut self, deadline: Option<Instant>, waker: &Waker) {
+        let (state, condvar) = &*self.shared;
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        if state.deadline == deadline
+            && state
+                .waker
+                .as_ref()
+                .is_some_and(|armed| armed.will_wake(waker))
+        {
+            return;
+        }
+        state.deadline = deadline;
+        state.waker = Some(waker.clone());
+        condvar.notify_one();
+
+        if deadline.is_some() && !self.started {
+            self.started = true;
+            let shared = self.shared.clone();
+            thread::spawn(move || wait_for_deadlines(&shared));
+        }
+    }
+}
+
+impl Drop for TimerThread {
+    fn drop(&mut self) {
+        let (state, condvar) = &*self.shared;
+        if let Ok(mut state) = state.lock() {
+            state.closed = true;
+            condvar.notify_one();
+        }
+    }
+}
+
+fn wait_for_deadlines((state, condvar): &(Mutex<TimerState>, Condvar)) {
+    loop {
+        let Ok(mut guard) = state.lock() else {
+            return;
+        };
+        let due = loop {
+            if guard.closed {
+                return;
+            }
+            let now = Instant::now();
+            guard = match guard.deadline {
+                Some(deadline) if deadline <= now => {
+                    guard.deadline = None;
+                    break guard.waker.clone();
+                }
+                Some(deadline) => match condvar.wait_timeout(guard, deadline - now) {
+                    Ok((guard, _)) => guard,
+                    Err(_) => return,
+                },
+                None => match condvar.wait(guard) {
+                    Ok(guard) => guard,
+                    Err(_) => return,
+                },
+            };
+        };
+
+        // Wake without the lock, the task may arm the next deadline right away
+        drop(guard);
+        if let Some(waker) = due {
+            waker.wake();
+        }
+    }
+}
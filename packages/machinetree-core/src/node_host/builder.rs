@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use crate::{
+    embeddable::context_holder::{ContextContainer, ContextHolder},
+    error::MachineTreeError,
+    key::Seed,
+    persistence::{ComponentRegistry, FileStore},
+};
+
+use super::{
+    clock::{Clock, HostClock, ManualClock},
+    scheduler::{FifoScheduler, RandomScheduler, Scheduler},
+    simulation::{SimRng, Simulation},
+    NodeHost,
+};
+
+// Configures a NodeHost before its root is mounted, obtained through NodeHost::builder
+pub struct NodeHostBuilder {
+    pub(crate) scheduler: Box<dyn Scheduler>,
+    pub(crate) root_context: ContextHolder,
+    pub(crate) clock: HostClock,
+    pub(crate) simulation: Option<Simulation>,
+    pub(crate) registry: Option<Arc<ComponentRegistry>>,
+    pub(crate) store: Option<FileStore>,
+}
+
+impl Default for NodeHostBuilder {
+    fn default() -> Self {
+        Self {
+            scheduler: Box::new(FifoScheduler::default()),
+            root_context: Default::default(),
+            clock: Default::default(),
+            simulation: None,
+            registry: None,
+            store: None,
+        }
+    }
+}
+
+impl NodeHostBuilder {
+    pub fn scheduler(mut self, scheduler: impl Scheduler + 'static) -> Self {
+        self.scheduler = Box::new(scheduler);
+        self
+    }
+
+    // Timers are checked whenever the host polls for work, the host cannot sleep until their
+    // deadlines in the time of another clock. Same as providing the clock as the ClockContext.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = HostClock::Custom(Arc::new(clock));
+        self
+    }
+
+    // Use a ManualClock, moved forward by NodeHost::advance_time
+    pub fn manual_clock(mut self, clock: Arc<ManualClock>) -> Self {
+        self.clock = HostClock::Manual(clock);
+        self
+    }
+
+    // Drive the scheduling order and the interleaving of external work from `seed`, so that a
+    // run can be replayed from its seed alone. The clock is a ManualClock starting at the time
+    // the host is built, which only moves through NodeHost::advance_time; timers fall due in
+    // the same order whatever the seed, the scheduler then picks among them.
+    pub fn simulation(mut self, seed: u64) -> Self {
+        let mut rng = SimRng::new(seed);
+        self.scheduler = Box::new(RandomScheduler::new(rng.next_u64()));
+        self.simulation = Some(Simulation::new(seed, rng));
+        self.manual_clock(Default::default())
+    }
+
+    // Provide a context above the root, visible to every node of the tree.
+    // Providing the ClockContext sets the clock of the host.
+    pub fn provide<Container>(mut self, value: Container::Inner) -> Self
+    where
+        Container: ContextContainer,
+    {
+        if let Some(clock) = HostClock::provided::<Container>(&value) {
+            self.clock = clock;
+        }
+        self.root_context.set::<Container>(value);
+        self
+    }
+
+    // Components and contexts known to NodeHost::snapshot and restore
+    pub fn registry(mut self, registry: Arc<ComponentRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    // Log every committed render pass to `store`, replacing what it held.
+    // Every node of the tree must be registered in the registry.
+    pub fn store(mut self, store: FileStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn root(self, seed: Seed) -> NodeHost {
+        NodeHost::from_builder(self, seed)
+    }
+
+    // Rebuild the tree written by NodeHost::snapshot, with the components of the registry
+    pub fn restore(self, bytes: &[u8]) -> Result<NodeHost, MachineTreeError> {
+        NodeHost::from_snapshot(self, serde_json::from_slice(bytes)?)
+    }
+
+    // Rebuild the tree held by `store` and keep logging to it
+    pub fn recover(self, store: FileStore) -> Result<NodeHost, MachineTreeError> {
+        NodeHost::from_store(self, store)
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    any::{Any, TypeId},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    embeddable::context_holder::ContextContainer, key::Seed, node::Component,
+    node_host::NodeControl,
+};
+
+// Source of time for the timers of a NodeHost, provided to the tree as the ClockContext
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Clock that only moves when told to, see NodeHost::advance_time
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl ManualClock {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    // Moves the clock to `instant`, never backwards
+    pub fn advance_to(&self, instant: Instant) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now = (*now).max(instant);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Context holding the host's clock, read with `use_context().get_context::<ClockContext>()`
+pub struct ClockContext;
+
+impl Component for ClockContext {
+    type Input = SharedClock;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        ClockContext
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+impl ContextContainer for ClockContext {
+    type Inner = SharedClock;
+}
+
+// Clock used by the host, provided to the tree as the ClockContext. Only the system clock
+// follows the time the host sleeps, the others move on their own.
+#[derive(Clone, Default)]
+pub(crate) enum HostClock {
+    #[default]
+    System,
+    // Kept around to advance it, see NodeHost::advance_time
+    Manual(Arc<ManualClock>),
+    Custom(SharedClock),
+}
+
+impl HostClock {
+    // The clock of a context provided as `Container`, if it is the ClockContext
+    pub(crate) fn provided<Container>(value: &Container::Inner) -> Option<HostClock>
+    where
+        Container: ContextContainer,
+    {
+        if TypeId::of::<Container>() != TypeId::of::<ClockContext>() {
+            return None;
+        }
+        let clock = (value as &dyn Any).downcast_ref::<SharedClock>()?;
+        Some(HostClock::Custom(clock.clone()))
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        match self {
+            HostClock::System => Instant::now(),
+            HostClock::Manual(clock) => clock.now(),
+            HostClock::Custom(clock) => clock.now(),
+        }
+    }
+
+    pub(crate) fn shared(&self) -> SharedClock {
+        match self {
+            HostClock::System => Arc::new(RealClock),
+            HostClock::Manual(clock) => clock.clone(),
+            HostClock::Custom(clock) => clock.clone(),
+        }
+    }
+
+    pub(crate) fn manual(&self) -> Option<Arc<ManualClock>> {
+        match self {
+            HostClock::Manual(clock) => Some(clock.clone()),
+            _ => None,
+        }
+    }
+
+    // Whether deadlines can be waited for by sleeping
+    pub(crate) fn is_real(&self) -> bool {
+        matches!(self, HostClock::System)
+    }
+}
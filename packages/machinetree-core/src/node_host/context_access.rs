@@ -11,7 +11,7 @@ pub struct NodeNavigator {
 impl NodeNavigator {
     pub fn get_parent(&self, lake: &NodeLake) -> Option<NodeNavigator> {
         let data = lake.get(&self.current)?;
-        let parent_wcc = (&data.borrow_self().borrow_relations().parent).clone()?;
+        let parent_wcc = data.borrow_self().borrow_relations().parent.clone()?;
         let parent_rcc = parent_wcc.upgrade()?;
         Some(NodeNavigator {
             current: parent_rcc.into(),
@@ -33,20 +33,81 @@ impl<'a> ContextAccess<'a> {
     {
         let node_data = self.lake.get(&self.node_key_pointer);
 
-        match node_data {
+        let previous = match node_data {
             Some(node_data) => {
                 let node_data_point = node_data.borrow_self();
                 let mut context_holder = node_data_point.borrow_mut_context();
-                (*context_holder).set::<Container>(value)
+                let previous = (*context_holder).set::<Container>(value);
+
+                // Nodes that read the previous value are stale now
+                let stale_readers = context_holder.take_subscribers::<Container>();
+                self.lake.push_context_rerenders(stale_readers);
+
+                previous
             }
-            None => None,
+            None => return None,
+        };
+
+        if previous.is_none() {
+            self.reschedule_shadowed::<Container>();
         }
+        previous
     }
 
-    pub fn get_context<'b, Container, ReturnValue>(&'a self) -> Option<Rc<Container::Inner>>
+    // The current node just started providing the context. Its descendants that read the value
+    // of an ancestor are stale now, they subscribe to the current node on their next read.
+    fn reschedule_shadowed<Container>(&self)
+    where
+        Container: ContextContainer,
+    {
+        let is_descendant = |reader: &Key| {
+            let mut current = reader.clone();
+            while let Some(parent) = self.lake.parent_of(&current) {
+                if parent == self.node_key_pointer {
+                    return true;
+                }
+                current = parent;
+            }
+            false
+        };
+
+        let mut maybe_navigator = NodeNavigator {
+            current: self.node_key_pointer.clone(),
+        }
+        .get_parent(self.lake);
+
+        let shadowed_readers = loop {
+            match maybe_navigator {
+                Some(navigator) => {
+                    if let Some(node_data) = self.lake.get(&navigator.current) {
+                        let node_data_point = node_data.borrow_self();
+                        let mut context_holder = node_data_point.borrow_mut_context();
+                        if context_holder.get::<Container>().is_some() {
+                            break context_holder
+                                .take_subscribers_where::<Container>(is_descendant);
+                        }
+                    }
+                    maybe_navigator = navigator.get_parent(self.lake);
+                }
+                None => {
+                    break self
+                        .lake
+                        .root_context
+                        .borrow_mut()
+                        .take_subscribers_where::<Container>(is_descendant);
+                }
+            }
+        };
+
+        self.lake.push_context_rerenders(shadowed_readers);
+    }
+
+    // Find the closest provided value, starting from the current node up to the root and then
+    // the contexts provided through the NodeHostBuilder.
+    // The current node is subscribed to the provider and is rerendered when it sets a new value.
+    pub fn get_context<Container>(&self) -> Option<Rc<Container::Inner>>
     where
         Container: ContextContainer,
-        'a: 'b,
     {
         let mut maybe_navigator = Some(NodeNavigator {
             current: self.node_key_pointer.clone(),
@@ -59,15 +120,24 @@ impl<'a> ContextAccess<'a> {
 
                     if let Some(node_data) = node_data {
                         let node_data_point = node_data.borrow_self();
-                        let context_holder = node_data_point.borrow_mut_context();
+                        let mut context_holder = node_data_point.borrow_mut_context();
                         if let Some(context_data) = context_holder.get::<Container>() {
+                            if navigator.current != self.node_key_pointer {
+                                context_holder.subscribe::<Container>(&self.node_key_pointer);
+                            }
                             break Some(context_data.clone());
                         }
                     }
 
                     navigator.get_parent(self.lake)
                 } else {
-                    break None;
+                    // Past the root, fall back to contexts provided by the host
+                    let mut root_context = self.lake.root_context.borrow_mut();
+                    let context_data = root_context.get::<Container>();
+                    if context_data.is_some() {
+                        root_context.subscribe::<Container>(&self.node_key_pointer);
+                    }
+                    break context_data;
                 }
             };
 
@@ -0,0 +1,176 @@
+use std::{
+    any::Any,
+    error::Error,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use crate::key::{Key, KeyWeak};
+
+use super::{lake::NodeLake, NodeControl};
+
+pub type StepError = Box<dyn Error + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub enum FailureCause {
+    Panic(String),
+    Error(Arc<dyn Error + Send + Sync>),
+}
+
+impl FailureCause {
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map_or("unknown panic", |message| message)
+                .to_string(),
+        };
+        FailureCause::Panic(message)
+    }
+}
+
+impl From<StepError> for FailureCause {
+    fn from(error: StepError) -> Self {
+        FailureCause::Error(error.into())
+    }
+}
+
+impl Display for FailureCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureCause::Panic(message) => write!(f, "panicked: {}", message),
+            FailureCause::Error(error) => write!(f, "returned an error: {}", error),
+        }
+    }
+}
+
+// A step that panicked or returned an Err through Component::try_step
+#[derive(Clone, Debug)]
+pub struct StepFailure {
+    pub key: Key,
+    pub cause: FailureCause,
+}
+
+impl Display for StepFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.key.debug_attempt_get_name(), self.cause)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureAction {
+    // Leave the failure to the next ancestor
+    Propagate,
+    // Unmount the child holding the failing node and step this node again
+    UnmountChild,
+    // Same as UnmountChild for the children in scope, reported as a RestartEvent.
+    // Seeds stepped again under the unmounted keys sprout fresh nodes.
+    Restart(RestartScope),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartScope {
+    // The child holding the failing node
+    Child,
+    // Every child
+    AllChildren,
+    // The child holding the failing node and the children after it
+    ChildAndFollowing,
+}
+
+// A node restarting its children, listed in RenderReport::restarts
+pub struct RestartEvent {
+    pub supervisor: Key,
+    pub failure: StepFailure,
+    // Keys of the unmounted children, in child order
+    pub restarted: Vec<Key>,
+}
+
+// Where a failure ends up, listed in RenderReport::failures
+pub struct FailureReport {
+    pub failure: StepFailure,
+    // None if no ancestor handled the failure, the failing node then keeps its previous children
+    pub boundary: Option<Key>,
+}
+
+pub(crate) struct Boundary {
+    pub(crate) boundary_key: Key,
+    // Child of the boundary whose subtree holds the failing node
+    pub(crate) child_key: Key,
+    pub(crate) action: FailureAction,
+}
+
+impl Boundary {
+    // Remove the children the action applies to from the boundary's relations, returning them
+    pub(crate) fn detach_children(&self, lake: &NodeLake) -> Vec<Key> {
+        let boundary_data = match lake.get(&self.boundary_key) {
+            Some(boundary_data) => boundary_data,
+            None => return vec![],
+        };
+        let boundary_data_point = boundary_data.borrow_self();
+        let mut relations = boundary_data_point.borrow_mut_relations();
+        let child_key_weak = KeyWeak::from(&self.child_key);
+        let child_index = relations
+            .children
+            .iter()
+            .position(|child| child.ptr_eq(&child_key_weak));
+
+        let detached_range = match (self.action, child_index) {
+            (_, None) => return vec![self.child_key.clone()],
+            (FailureAction::Restart(RestartScope::AllChildren), _) => 0..relations.children.len(),
+            (FailureAction::Restart(RestartScope::ChildAndFollowing), Some(index)) => {
+                index..relations.children.len()
+            }
+            (_, Some(index)) => index..index + 1,
+        };
+
+        relations
+            .children
+            .drain(detached_range)
+            .filter_map(|child| -> Option<Key> { (&child).try_into().ok() })
+            .collect()
+    }
+}
+
+// Ask the ancestors of the failing node, closest first, until one of them handles the failure
+pub(crate) fn find_boundary(lake: &NodeLake, failure: &StepFailure) -> Option<Boundary> {
+    let mut child_key = failure.key.clone();
+
+    loop {
+        let parent_key = lake.parent_of(&child_key)?;
+        let parent_data = lake.get(&parent_key)?;
+        let action = {
+            let parent_data_point = parent_data.borrow_self();
+            let parent_data_borrow = parent_data_point.borrow_data_mut();
+            let mut control = NodeControl::new(lake, parent_key.clone());
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                parent_data_borrow
+                    .component
+                    .borrow_mut()
+                    .on_descendant_failure(&mut control, failure)
+            }))
+        };
+
+        // A handler that panics leaves the failure to the next ancestor
+        let action = action.unwrap_or_else(|payload| {
+            lake.push_failure(StepFailure {
+                key: parent_key.clone(),
+                cause: FailureCause::from_panic(payload),
+            });
+            FailureAction::Propagate
+        });
+
+        match action {
+            FailureAction::Propagate => child_key = parent_key,
+            action => {
+                return Some(Boundary {
+                    boundary_key: parent_key,
+                    child_key,
+                    action,
+                })
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use crate::{
-    embeddable::context_holder::ContextHolder,
-    key::{Key, KeyArc, KeyWeak, RawData, Seed},
+    embeddable::{
+        context_holder::ContextHolder, effect_manager::EffectManager, state_manager::StateManager,
+    },
+    key::{AnyMessage, Key, KeyWeak, RawData, RawKey, Seed},
 };
+
+use super::{clock::HostClock, failure::StepFailure, timer::TimerQueue};
 use std::{
-    cell::{Ref, RefCell, RefMut},
-    collections::HashMap,
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::{HashMap, VecDeque},
     rc::Rc,
 };
 
@@ -15,25 +19,55 @@ pub struct NodeRelations {
 }
 
 pub struct NodeDataPoint {
+    // Stable across snapshots, unlike the key
+    pub(crate) id: u64,
     pub(crate) self_data: Rc<RefCell<RawData>>,
     pub(crate) context_holder: Rc<RefCell<ContextHolder>>,
     pub(crate) relations: Rc<RefCell<NodeRelations>>,
+    pub(crate) effect_manager: Rc<RefCell<EffectManager>>,
+    pub(crate) state_manager: Rc<RefCell<StateManager>>,
+    pub(crate) mailbox: RefCell<VecDeque<AnyMessage>>,
+    pub(crate) priority: Cell<i32>,
 }
 
 impl NodeDataPoint {
-    pub(crate) fn borrow_relations<'a>(&'a self) -> Ref<NodeRelations> {
+    pub(crate) fn borrow_relations(&self) -> Ref<'_, NodeRelations> {
         self.relations.borrow()
     }
 
-    pub(crate) fn borrow_mut_relations<'a>(&'a self) -> RefMut<NodeRelations> {
+    pub(crate) fn borrow_mut_relations(&self) -> RefMut<'_, NodeRelations> {
         self.relations.borrow_mut()
     }
 
-    pub(crate) fn borrow_mut_context<'a>(&'a self) -> RefMut<ContextHolder> {
+    pub(crate) fn borrow_mut_context(&self) -> RefMut<'_, ContextHolder> {
         self.context_holder.borrow_mut()
     }
 
-    pub(crate) fn borrow_data_mut<'a>(&'a self) -> RefMut<RawData> {
+    pub(crate) fn borrow_mut_effects(&self) -> RefMut<'_, EffectManager> {
+        self.effect_manager.borrow_mut()
+    }
+
+    pub(crate) fn borrow_mut_state(&self) -> RefMut<'_, StateManager> {
+        self.state_manager.borrow_mut()
+    }
+
+    pub(crate) fn push_message(&self, message: AnyMessage) {
+        self.mailbox.borrow_mut().push_back(message);
+    }
+
+    pub(crate) fn take_messages(&self) -> VecDeque<AnyMessage> {
+        std::mem::take(&mut *self.mailbox.borrow_mut())
+    }
+
+    pub(crate) fn borrow_data(&self) -> Ref<'_, RawData> {
+        self.self_data.borrow()
+    }
+
+    pub(crate) fn borrow_context(&self) -> Ref<'_, ContextHolder> {
+        self.context_holder.borrow()
+    }
+
+    pub(crate) fn borrow_data_mut(&self) -> RefMut<'_, RawData> {
         self.self_data.borrow_mut()
     }
 }
@@ -56,23 +90,73 @@ impl NodeData {
 #[derive(Default)]
 pub struct NodeLake {
     pub(crate) data_map: HashMap<Key, NodeData>,
+    // Readers of contexts that were set since the host last collected them
+    pub(crate) context_rerenders: RefCell<Vec<Key>>,
+    // Nodes whose input was replaced since the host last logged them, stepped or not
+    pub(crate) merged_inputs: RefCell<Vec<Key>>,
+    // Panics of on_unmount and on_descendant_failure, reported by the next render pass
+    pub(crate) unreported_failures: RefCell<Vec<StepFailure>>,
+    // Contexts provided by the embedding application, above the root
+    pub(crate) root_context: RefCell<ContextHolder>,
+    pub(crate) timers: RefCell<TimerQueue>,
+    pub(crate) clock: HostClock,
+    pub(crate) next_node_id: u64,
 }
 
 impl NodeLake {
+    pub(crate) fn push_context_rerenders(&self, node_keys: Vec<Key>) {
+        self.context_rerenders.borrow_mut().extend(node_keys);
+    }
+
+    pub(crate) fn take_context_rerenders(&self) -> Vec<Key> {
+        std::mem::take(&mut *self.context_rerenders.borrow_mut())
+    }
+
+    pub(crate) fn push_merged_input(&self, node_key: Key) {
+        self.merged_inputs.borrow_mut().push(node_key);
+    }
+
+    pub(crate) fn take_merged_inputs(&self) -> Vec<Key> {
+        std::mem::take(&mut *self.merged_inputs.borrow_mut())
+    }
+
+    pub(crate) fn push_failure(&self, failure: StepFailure) {
+        self.unreported_failures.borrow_mut().push(failure);
+    }
+
+    pub(crate) fn take_failures(&self) -> Vec<StepFailure> {
+        std::mem::take(&mut *self.unreported_failures.borrow_mut())
+    }
+
     pub(crate) fn remove(&mut self, node_key: &Key) -> Option<NodeData> {
         self.data_map.remove(node_key)
     }
 
     pub(crate) fn sprout_and_link(&mut self, node_seed: Seed) -> (Key, NodeData) {
         let (raw_key, raw_data) = node_seed.sprout();
-        let node_key_arc: KeyArc = raw_key.into();
+        let id = self.next_node_id;
+        self.insert_raw(id, raw_key, raw_data)
+    }
+
+    pub(crate) fn insert_raw(
+        &mut self,
+        id: u64,
+        raw_key: RawKey,
+        raw_data: RawData,
+    ) -> (Key, NodeData) {
+        self.next_node_id = self.next_node_id.max(id + 1);
         let node_key = Key::new_from_raw(raw_key);
 
         let node_data_pointer = self.entry(node_key.clone()).or_insert(
             NodeDataPoint {
+                id,
                 self_data: raw_data.into(),
                 context_holder: Default::default(),
                 relations: Default::default(),
+                effect_manager: Default::default(),
+                state_manager: Default::default(),
+                mailbox: Default::default(),
+                priority: Default::default(),
             }
             .into(),
         );
@@ -80,14 +164,46 @@ impl NodeLake {
         (node_key, node_data_pointer.clone())
     }
 
-    pub(crate) fn get<'a>(&'a self, key: &Key) -> Option<NodeData> {
-        self.data_map.get(key).map(|x| x.clone())
+    pub(crate) fn id_of(&self, key: &Key) -> Option<u64> {
+        self.get(key).map(|data| data.borrow_self().id)
+    }
+
+    pub(crate) fn get(&self, key: &Key) -> Option<NodeData> {
+        self.data_map.get(key).cloned()
+    }
+
+    pub(crate) fn parent_of(&self, key: &Key) -> Option<Key> {
+        let parent = self
+            .get(key)?
+            .borrow_self()
+            .borrow_relations()
+            .parent
+            .clone()?;
+        Key::try_from(&parent).ok()
+    }
+
+    pub(crate) fn depth_of(&self, key: &Key) -> usize {
+        let mut depth = 0;
+        let mut current = key.clone();
+
+        while let Some(parent) = self.parent_of(&current) {
+            depth += 1;
+            current = parent;
+        }
+
+        depth
+    }
+
+    pub(crate) fn priority_of(&self, key: &Key) -> i32 {
+        self.get(key)
+            .map(|data| data.borrow_self().priority.get())
+            .unwrap_or_default()
     }
 
     pub(crate) fn entry<'a>(
         &'a mut self,
         node_rcc: Key,
     ) -> std::collections::hash_map::Entry<'a, Key, NodeData> {
-        self.data_map.entry(Key::from(node_rcc))
+        self.data_map.entry(node_rcc)
     }
 }
@@ -0,0 +1,84 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::key::Key;
+
+use super::{
+    failure::{FailureCause, StepFailure},
+    lake::NodeLake,
+    NodeControl, NodeControlResult,
+};
+
+// Runs Component::on_mount if the node has not been mounted yet. A node whose on_mount
+// panicked stays unmounted.
+pub(crate) fn mount(
+    lake: &NodeLake,
+    node_key: &Key,
+) -> Result<Option<NodeControlResult>, FailureCause> {
+    let node_data = match lake.get(node_key) {
+        Some(node_data) => node_data,
+        None => return Ok(None),
+    };
+    let node_data_point = node_data.borrow_self();
+    let mut node_data_borrow = node_data_point.borrow_data_mut();
+
+    if node_data_borrow.mounted {
+        return Ok(None);
+    }
+
+    let mut control = NodeControl::new(lake, node_key.clone());
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        node_data_borrow
+            .component
+            .borrow_mut()
+            .on_mount(&mut control)
+    }))
+    .map_err(FailureCause::from_panic)?;
+    node_data_borrow.mounted = true;
+
+    Ok(Some(control.into()))
+}
+
+// Runs Component::on_unmount if the node has been mounted, after cleaning up its effects, then
+// cancels its timers, including those set by on_unmount.
+// The node is removed either way, so panics are only reported.
+pub(crate) fn unmount(lake: &NodeLake, node_key: &Key) {
+    let node_data = match lake.get(node_key) {
+        Some(node_data) => node_data,
+        None => return,
+    };
+    let node_data_point = node_data.borrow_self();
+
+    let unmounted = panic::catch_unwind(AssertUnwindSafe(|| {
+        node_data_point.borrow_mut_effects().cleanup_all();
+
+        let mut node_data_borrow = node_data_point.borrow_data_mut();
+        if !node_data_borrow.mounted {
+            return;
+        }
+        node_data_borrow.mounted = false;
+
+        let mut control = NodeControl::new(lake, node_key.clone());
+        node_data_borrow
+            .component
+            .borrow_mut()
+            .on_unmount(&mut control);
+    }));
+    lake.timers.borrow_mut().cancel_node(node_key);
+
+    if let Err(payload) = unmounted {
+        lake.push_failure(StepFailure {
+            key: node_key.clone(),
+            cause: FailureCause::from_panic(payload),
+        });
+    }
+}
+
+pub(crate) fn flush_effects(lake: &NodeLake, node_key: &Key) -> Result<(), FailureCause> {
+    match lake.get(node_key) {
+        Some(node_data) => panic::catch_unwind(AssertUnwindSafe(|| {
+            node_data.borrow_self().borrow_mut_effects().flush()
+        }))
+        .map_err(FailureCause::from_panic),
+        None => Ok(()),
+    }
+}
@@ -1,37 +1,228 @@
+pub mod async_host;
+pub mod builder;
+pub mod clock;
 pub mod context_access;
+pub mod failure;
 mod lake;
+mod lifecycle;
+mod persist;
+mod reload;
 mod render;
+pub mod scheduler;
+pub mod simulation;
+pub mod timer;
 
 use crate::{
-    key::{Key, Seed},
-    node::WorkItem,
+    embeddable::{
+        context_holder::ContextContainer,
+        effect_manager::{EffectCleanup, EffectRegistration},
+        state_manager::StateHandle,
+    },
+    error::MachineTreeError,
+    key::{AnyBox, Key, Seed},
+    node::{
+        Component, ExternalSender, ExternalWorkItem, Mailbox, NodeHandle, NodeHandleError, WorkItem,
+    },
+    persistence::{ComponentRegistry, FileStore},
 };
 use lake::NodeLake;
 use std::{
+    any::TypeId,
+    cell::RefCell,
     collections::{HashSet, VecDeque},
+    convert::Infallible,
     fmt::Display,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
     vec,
 };
 
-use self::{context_access::ContextAccess, render::UnlinkedPair};
+use self::{
+    builder::NodeHostBuilder,
+    clock::{Clock, ClockContext, HostClock},
+    context_access::ContextAccess,
+    failure::{FailureAction, FailureReport, RestartEvent, StepFailure},
+    render::UnlinkedPair,
+    scheduler::{Scheduler, WorkInfo},
+    simulation::Simulation,
+    timer::TimerId,
+};
 
 pub struct NodeControl<'a> {
     lake: &'a NodeLake,
     current: Key,
     rerender_flag: bool,
+    effects: Vec<EffectRegistration>,
+    state_cursor: usize,
+    previous_input: Option<&'a AnyBox>,
+    // Hooks are matched by call order within a step, so other callbacks cannot use them
+    in_step: bool,
 }
 
 impl<'a> NodeControl<'a> {
-    pub fn rerender(&mut self) -> () {
+    pub(crate) fn new(lake: &'a NodeLake, current: Key) -> Self {
+        Self {
+            lake,
+            current,
+            rerender_flag: false,
+            effects: vec![],
+            state_cursor: 0,
+            previous_input: None,
+            in_step: false,
+        }
+    }
+
+    pub(crate) fn for_step(lake: &'a NodeLake, current: Key) -> Self {
+        Self {
+            in_step: true,
+            ..Self::new(lake, current)
+        }
+    }
+
+    fn assert_in_step(&self, hook: &str) {
+        assert!(
+            self.in_step,
+            "NodeControl::{} can only be called from Component::step",
+            hook
+        );
+    }
+
+    pub fn rerender(&mut self) {
         self.rerender_flag = true;
     }
 
-    pub fn use_context<'b>(&'a mut self) -> ContextAccess
+    // Input received by the node's previous step, available when the parent has passed a new
+    // input since. None on the first step, on self-triggered steps, and if `Input` is not the
+    // component's input type.
+    pub fn previous_input<Input>(&self) -> Option<&Input>
+    where
+        Input: 'static,
+    {
+        self.previous_input?.downcast_ref::<Input>()
+    }
+
+    pub fn set_priority(&mut self, priority: i32) {
+        if let Some(node_data) = self.lake.get(&self.current) {
+            node_data.borrow_self().priority.set(priority);
+        }
+    }
+
+    // Register an effect to run after the render pass is committed.
+    // It runs again only when `deps` differs from the previous step's, and the
+    // cleanup it returns runs before the next run and when the node is unmounted.
+    // Effects are matched by registration order, so register the same effects on every step.
+    // Panics outside of step, failing the node.
+    pub fn use_effect<Deps, Effect>(&mut self, deps: Deps, effect: Effect)
+    where
+        Deps: PartialEq + 'static,
+        Effect: FnOnce() -> Option<EffectCleanup> + 'static,
+    {
+        self.assert_in_step("use_effect");
+        self.effects
+            .push(EffectRegistration::new(deps, Box::new(effect)));
+    }
+
+    // Get the node's state at this call position, created by `init` on first use.
+    // States are matched by call order, so request the same states on every step.
+    // Panics outside of step, failing the node.
+    pub fn use_state<T, Init>(&mut self, init: Init) -> StateHandle<T>
+    where
+        T: Send + 'static,
+        Init: FnOnce() -> T,
+    {
+        self.assert_in_step("use_state");
+        let index = self.state_cursor;
+        self.state_cursor += 1;
+
+        let value = match self.lake.get(&self.current) {
+            Some(node_data) => node_data.borrow_self().borrow_mut_state().slot(index, init),
+            None => Arc::new(Mutex::new(init())),
+        };
+
+        StateHandle {
+            value,
+            handle: self.handle(),
+        }
+    }
+
+    // Thread-safe handle to the current node, usable after the step returns
+    pub fn handle(&self) -> NodeHandle {
+        NodeHandle::new(&self.current)
+    }
+
+    // Current time of the host's clock
+    pub fn now(&self) -> Instant {
+        self.lake.clock.now()
+    }
+
+    // Rerender the node once `delay` has elapsed
+    pub fn set_timeout(&mut self, delay: Duration) -> TimerId {
+        self.lake
+            .timers
+            .borrow_mut()
+            .insert(self.current.clone(), self.now() + delay, None)
+    }
+
+    // Rerender the node every `period` until the timer is cleared or the node is unmounted.
+    // Every call adds a timer, set intervals from on_mount or keep the returned id around.
+    // Periods shorter than MIN_INTERVAL are raised to it, as an interval due again right away
+    // would keep the host from ever being idle.
+    pub fn set_interval(&mut self, period: Duration) -> TimerId {
+        let period = period.max(timer::MIN_INTERVAL);
+        self.lake.timers.borrow_mut().insert(
+            self.current.clone(),
+            self.now() + period,
+            Some(period),
+        )
+    }
+
+    pub fn clear_timer(&mut self, timer_id: TimerId) {
+        self.lake.timers.borrow_mut().cancel(timer_id);
+    }
+
+    pub fn waker(&self) -> Waker {
+        self.handle().waker()
+    }
+
+    // Poll a future owned by the component with the node's waker.
+    // When it returns Pending, the node is rerendered once the future is woken.
+    pub fn poll<F>(&self, future: Pin<&mut F>) -> Poll<F::Output>
     where
-        'a: 'b,
+        F: Future + ?Sized,
     {
+        let waker = self.waker();
+        future.poll(&mut Context::from_waker(&waker))
+    }
+
+    // Mailbox accepting the messages of the current node's component, fails with
+    // MachineTreeError::TypeMismatch if `Machine` is another component
+    pub fn mailbox<Machine>(&self) -> Result<Mailbox<Machine::Message>, MachineTreeError>
+    where
+        Machine: Component,
+    {
+        let type_id = self
+            .current
+            .lock()
+            .map_err(|_| MachineTreeError::PoisonedKey)?
+            .type_id;
+        if type_id != TypeId::of::<Machine>() {
+            return Err(MachineTreeError::TypeMismatch);
+        }
+
+        Ok(Mailbox {
+            handle: self.handle(),
+            message_type: PhantomData,
+        })
+    }
+
+    pub fn use_context(&self) -> ContextAccess<'a> {
         ContextAccess {
-            lake: &*self.lake,
+            lake: self.lake,
             node_key_pointer: self.current.clone(),
         }
     }
@@ -41,12 +232,14 @@ impl From<NodeControl<'_>> for NodeControlResult {
     fn from(control: NodeControl) -> Self {
         Self {
             rerender_flag: control.rerender_flag,
+            effects: control.effects,
         }
     }
 }
 
 pub struct NodeControlResult {
     rerender_flag: bool,
+    effects: Vec<EffectRegistration>,
 }
 
 #[derive(Default)]
@@ -54,6 +247,57 @@ pub struct RenderReport {
     pub unrendered_keys: Vec<Key>,
     pub rendered_keys: Vec<Key>,
     pub unlinked_node_pairs: Vec<UnlinkedPair>,
+    pub failures: Vec<FailureReport>,
+    pub restarts: Vec<RestartEvent>,
+    // The pass is rendered but could not be written to the host's FileStore
+    pub storage_failures: Vec<MachineTreeError>,
+}
+
+// Entries kept in each list of the report of NodeHost::run
+pub const RUN_REPORT_LIMIT: usize = 1024;
+
+#[derive(Clone, Copy)]
+struct DrainParam {
+    stop_on_shutdown: bool,
+    // Wall time to stop at, even with work left
+    deadline: Option<Instant>,
+    // Drop unlinked nodes and older entries of the report, for runs without end
+    bounded_report: bool,
+}
+
+impl DrainParam {
+    const UNTIL_IDLE: DrainParam = DrainParam {
+        stop_on_shutdown: false,
+        deadline: None,
+        bounded_report: false,
+    };
+}
+
+impl RenderReport {
+    // Move the content of a later report at the end of this one
+    pub fn append(&mut self, mut other: RenderReport) {
+        self.unrendered_keys.append(&mut other.unrendered_keys);
+        self.rendered_keys.append(&mut other.rendered_keys);
+        self.unlinked_node_pairs
+            .append(&mut other.unlinked_node_pairs);
+        self.failures.append(&mut other.failures);
+        self.restarts.append(&mut other.restarts);
+        self.storage_failures.append(&mut other.storage_failures);
+    }
+
+    fn retain_latest(&mut self, limit: usize) {
+        fn retain<T>(entries: &mut Vec<T>, limit: usize) {
+            let excess = entries.len().saturating_sub(limit);
+            entries.drain(..excess);
+        }
+
+        retain(&mut self.unrendered_keys, limit);
+        retain(&mut self.rendered_keys, limit);
+        retain(&mut self.unlinked_node_pairs, limit);
+        retain(&mut self.failures, limit);
+        retain(&mut self.restarts, limit);
+        retain(&mut self.storage_failures, limit);
+    }
 }
 
 impl Display for RenderReport {
@@ -80,62 +324,291 @@ impl Display for RenderReport {
             .collect::<Vec<_>>()
             .join("");
 
+        let failures_string = self
+            .failures
+            .iter()
+            .map(|failure_report| format!("\n  - {}", &failure_report.failure))
+            .collect::<Vec<_>>()
+            .join("");
+        let restarts_string = self
+            .restarts
+            .iter()
+            .map(|restart| {
+                format!(
+                    "\n  - {} restarted {} children after {}",
+                    restart.supervisor.debug_attempt_get_name(),
+                    restart.restarted.len(),
+                    &restart.failure
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        let storage_failures_string = self
+            .storage_failures
+            .iter()
+            .map(|error| format!("\n  - {}", error))
+            .collect::<Vec<_>>()
+            .join("");
+
         f.write_fmt(format_args!(
-            "RenderReport:\n- RenderedKeys:{}\n- UnlinkedKeys:{}\n- UnrenderedKeys:{}",
-            &rendered_keys_string, &unlinked_keys_string, &unrendered_keys_string
+            "RenderReport:\n- RenderedKeys:{}\n- UnlinkedKeys:{}\n- UnrenderedKeys:{}\n- Failures:{}\n- Restarts:{}\n- StorageFailures:{}",
+            &rendered_keys_string,
+            &unlinked_keys_string,
+            &unrendered_keys_string,
+            &failures_string,
+            &restarts_string,
+            &storage_failures_string
         ))
     }
 }
 
 pub struct ExternalRenderWorkQueue {
-    sender: crossbeam::channel::Sender<Key>,
-    receiver: crossbeam::channel::Receiver<Key>,
+    sender: ExternalSender,
+    receiver: crossbeam::channel::Receiver<ExternalWorkItem>,
 }
 
 impl Default for ExternalRenderWorkQueue {
     fn default() -> Self {
         let (sender, receiver) = crossbeam::channel::unbounded();
-        Self { sender, receiver }
+        Self {
+            sender: ExternalSender {
+                sender,
+                waker: Default::default(),
+            },
+            receiver,
+        }
+    }
+}
+
+impl ExternalRenderWorkQueue {
+    // The waker is woken by every later send, until it is replaced or cleared
+    pub(crate) fn register_waker(&self, waker: Option<&Waker>) {
+        let mut registered = self
+            .sender
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match (registered.as_ref(), waker) {
+            (Some(registered), Some(waker)) if registered.will_wake(waker) => {}
+            _ => *registered = waker.cloned(),
+        }
+    }
+}
+
+// Stops NodeHost::run, or the stream of an AsyncNodeHost, from any thread. Requests that
+// arrive while the host renders otherwise, as in NodeHost::run_until_idle, are dropped.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    sender: ExternalSender,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) -> Result<(), NodeHandleError> {
+        self.sender
+            .send(ExternalWorkItem::Shutdown)
+            .map_err(|_| NodeHandleError::Disconnected)
     }
 }
 
 pub struct NodeHost {
     lake: NodeLake,
-    work_queue: VecDeque<WorkItem>,
+    root: Key,
+    scheduler: Box<dyn Scheduler>,
     external_render_work_queue: ExternalRenderWorkQueue,
+    shutdown_requested: bool,
+    simulation: Option<Simulation>,
+    registry: Option<Arc<ComponentRegistry>>,
+    store: Option<FileStore>,
 }
 
 impl NodeHost {
     pub fn make_root(seed: Seed) -> NodeHost {
-        let mut queue: VecDeque<WorkItem> = Default::default();
-        let mut lake: NodeLake = Default::default();
-        let (node_key, _) = lake.sprout_and_link(seed);
+        Self::builder().root(seed)
+    }
+
+    pub fn make_root_with_scheduler(seed: Seed, scheduler: impl Scheduler + 'static) -> NodeHost {
+        Self::builder().scheduler(scheduler).root(seed)
+    }
+
+    pub fn builder() -> NodeHostBuilder {
+        Default::default()
+    }
+
+    pub(crate) fn from_builder(builder: NodeHostBuilder, seed: Seed) -> NodeHost {
+        let Ok(mut host) = Self::with_root(builder, |lake| {
+            Ok::<_, Infallible>(lake.sprout_and_link(seed).0)
+        });
+        let root = host.root.clone();
+        host.schedule(WorkItem::Render(root));
 
-        queue.push_front(WorkItem::Render(node_key.into()));
+        host
+    }
+
+    // Assemble a host around the root that `insert_root` adds to the lake
+    pub(crate) fn with_root<Error>(
+        builder: NodeHostBuilder,
+        insert_root: impl FnOnce(&mut NodeLake) -> Result<Key, Error>,
+    ) -> Result<NodeHost, Error> {
+        let NodeHostBuilder {
+            scheduler,
+            mut root_context,
+            clock,
+            simulation,
+            registry,
+            store,
+        } = builder;
+        root_context.set::<ClockContext>(clock.shared());
+        let mut lake = NodeLake {
+            root_context: RefCell::new(root_context),
+            clock,
+            ..Default::default()
+        };
+        let external_render_work_queue = ExternalRenderWorkQueue::default();
+        let root = insert_root(&mut lake)?;
+
+        lake.data_map.keys().for_each(|node_key| {
+            if let Ok(mut node_key_raw) = node_key.lock() {
+                node_key_raw
+                    .self_render
+                    .set_self(node_key, &external_render_work_queue.sender);
+            }
+        });
 
-        NodeHost {
+        Ok(NodeHost {
             lake,
-            work_queue: queue,
-            external_render_work_queue: Default::default(),
+            root,
+            scheduler,
+            external_render_work_queue,
+            shutdown_requested: false,
+            simulation,
+            registry,
+            store,
+        })
+    }
+
+    // Replace a context provided above the root, rerendering the nodes that read it.
+    // Replacing the ClockContext replaces the clock of the host, pending timers keep their
+    // deadlines.
+    pub fn set_root_context<Container>(
+        &mut self,
+        value: Container::Inner,
+    ) -> Option<Rc<Container::Inner>>
+    where
+        Container: ContextContainer,
+    {
+        if let Some(clock) = HostClock::provided::<Container>(&value) {
+            self.lake.clock = clock;
         }
+        let (previous, stale_readers) = {
+            let mut root_context = self.lake.root_context.borrow_mut();
+            let previous = root_context.set::<Container>(value);
+            (previous, root_context.take_subscribers::<Container>())
+        };
+
+        let alive_readers = stale_readers
+            .into_iter()
+            .filter(|reader| self.lake.get(reader).is_some())
+            .collect::<Vec<_>>();
+        alive_readers
+            .into_iter()
+            .for_each(|reader| self.schedule(WorkItem::Render(reader)));
+
+        previous
+    }
+
+    fn schedule(&mut self, work: WorkItem) {
+        let info = match &work {
+            WorkItem::Render(node_key) => WorkInfo {
+                depth: self.lake.depth_of(node_key),
+                priority: self.lake.priority_of(node_key),
+            },
+        };
+        self.scheduler.push(work, info);
     }
 
     pub fn render(&mut self) -> RenderReport {
-        let work_opt = self.work_queue.pop_front();
+        let work_opt = self.scheduler.pop();
         match work_opt {
-            Some(work) => match work {
-                WorkItem::Render(x) => self.render_node(x),
-            },
+            Some(work) => {
+                let mut report = match work {
+                    WorkItem::Render(x) => self.render_node(x),
+                };
+                if let Err(error) = self.commit_pass(&report) {
+                    report.storage_failures.push(error);
+                }
+                report
+            }
             None => RenderReport::default(),
         }
     }
 
-    pub fn poll_work(&mut self) -> () {
+    // Same as render, but fails with the first failure that no ancestor handled, or with the
+    // failure to store the pass. The rest of the pass is committed regardless.
+    pub fn try_render(&mut self) -> Result<RenderReport, MachineTreeError> {
+        let mut report = self.render();
+        if !report.storage_failures.is_empty() {
+            return Err(report.storage_failures.remove(0));
+        }
+        match report
+            .failures
+            .iter()
+            .find(|failure_report| failure_report.boundary.is_none())
+        {
+            Some(failure_report) => Err(failure_report.failure.clone().into()),
+            None => Ok(report),
+        }
+    }
+
+    pub fn poll_work(&mut self) {
+        let due_nodes = self.lake.timers.borrow_mut().pop_due(self.lake.clock.now());
+        due_nodes
+            .into_iter()
+            .for_each(|node_key| self.schedule(WorkItem::Render(node_key)));
+
+        let mut received = self
+            .external_render_work_queue
+            .receiver
+            .try_iter()
+            .collect::<Vec<_>>();
+        if let Some(simulation) = &mut self.simulation {
+            received = simulation.interleave(received);
+        }
+        self.accept_external_work(received);
+    }
+
+    // Seed of a host built with NodeHostBuilder::simulation, replaying the same run
+    pub fn simulation_seed(&self) -> Option<u64> {
+        self.simulation.as_ref().map(|simulation| simulation.seed)
+    }
+
+    // Whether external work was held back by the simulation for a later poll
+    pub(crate) fn has_deferred_work(&self) -> bool {
+        self.simulation
+            .as_ref()
+            .is_some_and(Simulation::has_deferred_work)
+    }
+
+    fn accept_external_work(&mut self, received: Vec<ExternalWorkItem>) {
         let sources = {
             let mut sources: VecDeque<_> = vec![].into();
             let mut memo = HashSet::new();
 
-            while let Ok(key) = self.external_render_work_queue.receiver.try_recv() {
+            for work in received {
+                let key = match work {
+                    ExternalWorkItem::Render(key) => key,
+                    ExternalWorkItem::Message(key, message) => {
+                        // Messages wait in the node's mailbox until its next step
+                        match self.lake.get(&key) {
+                            Some(node_data) => node_data.borrow_self().push_message(message),
+                            None => continue,
+                        }
+                        key
+                    }
+                    ExternalWorkItem::Shutdown => {
+                        self.shutdown_requested = true;
+                        continue;
+                    }
+                };
                 let ptr = key.read_ptr_as_usize();
                 if !memo.contains(&ptr) {
                     memo.insert(ptr);
@@ -145,20 +618,184 @@ impl NodeHost {
             sources
         };
 
-        self.work_queue
-            .extend(&mut sources.into_iter().map(|source| WorkItem::Render(source)));
+        sources
+            .into_iter()
+            .for_each(|source| self.schedule(WorkItem::Render(source)));
+    }
+
+    // Render until neither the scheduler nor the external queue has work left, polling the
+    // external queue between renders. Never returns if nodes keep rerendering themselves.
+    pub fn run_until_idle(&mut self) -> RenderReport {
+        let mut report = RenderReport::default();
+        self.drain_work(&mut report, DrainParam::UNTIL_IDLE);
+        report
+    }
+
+    // Render work as it comes, blocking on the external queue while idle, until a ShutdownHandle
+    // requests it or `timeout` elapses, even while work keeps coming.
+    // The report holds the latest RUN_REPORT_LIMIT entries of each list, without the unlinked
+    // nodes which are dropped as soon as their pass is done.
+    pub fn run(&mut self, timeout: Option<Duration>) -> RenderReport {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut report = RenderReport::default();
+        let param = DrainParam {
+            stop_on_shutdown: true,
+            deadline,
+            bounded_report: true,
+        };
+
+        loop {
+            self.drain_work(&mut report, param);
+            if self.shutdown_requested {
+                self.shutdown_requested = false;
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+
+            // Sleep until external work comes, the next timer is due, or the run times out
+            let wake_at = match (deadline, self.timer_wake_deadline()) {
+                (Some(deadline), Some(timer)) => Some(deadline.min(timer)),
+                (deadline, timer) => deadline.or(timer),
+            };
+            let receiver = &self.external_render_work_queue.receiver;
+            let received = match wake_at {
+                Some(wake_at) => receiver.recv_deadline(wake_at).ok(),
+                None => receiver.recv().ok(),
+            };
+            match received {
+                Some(work) => self.accept_external_work(vec![work]),
+                None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => break,
+                None => {}
+            }
+        }
+
+        report
+    }
+
+    // When the earliest timer set through NodeControl is due, in the time of the host's clock
+    pub fn next_timer_deadline(&self) -> Option<Instant> {
+        self.lake.timers.borrow().next_deadline()
+    }
+
+    // Deadline of the next timer to sleep until, None with a manual clock which does not follow
+    // the time slept
+    pub(crate) fn timer_wake_deadline(&self) -> Option<Instant> {
+        match self.lake.clock.is_real() {
+            true => self.next_timer_deadline(),
+            false => None,
+        }
+    }
+
+    // Move the manual clock forward by `duration`, stopping at every timer deadline on the way
+    // to render the work due then
+    pub fn advance_time(&mut self, duration: Duration) -> Result<RenderReport, MachineTreeError> {
+        let manual_clock = self
+            .lake
+            .clock
+            .manual()
+            .ok_or(MachineTreeError::ClockNotManual)?;
+        let target = manual_clock.now() + duration;
+        let mut report = RenderReport::default();
+
+        while let Some(deadline) = self
+            .next_timer_deadline()
+            .filter(|deadline| *deadline <= target)
+        {
+            manual_clock.advance_to(deadline);
+            self.drain_work(&mut report, DrainParam::UNTIL_IDLE);
+        }
+
+        manual_clock.advance_to(target);
+        self.drain_work(&mut report, DrainParam::UNTIL_IDLE);
+
+        Ok(report)
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            sender: self.external_render_work_queue.sender.clone(),
+        }
+    }
+
+    fn drain_work(&mut self, report: &mut RenderReport, param: DrainParam) {
+        loop {
+            self.poll_work();
+            if self.shutdown_requested {
+                match param.stop_on_shutdown {
+                    true => break,
+                    false => self.shutdown_requested = false,
+                }
+            }
+            if param
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                break;
+            }
+            if self.scheduler.is_empty() {
+                match self.has_deferred_work() {
+                    true => continue,
+                    false => break,
+                }
+            }
+
+            let mut pass_report = self.render();
+            if param.bounded_report {
+                pass_report.unlinked_node_pairs.clear();
+                report.append(pass_report);
+                report.retain_latest(RUN_REPORT_LIMIT);
+            } else {
+                report.append(pass_report);
+            }
+        }
+    }
+
+    // Replace the failing subtree with whatever the boundary steps next, returning the
+    // boundary to step
+    fn handle_failure(&mut self, failure: StepFailure, report: &mut RenderReport) -> Option<Key> {
+        let boundary = failure::find_boundary(&self.lake, &failure);
+
+        if let Some(boundary) = &boundary {
+            let detached_children = boundary.detach_children(&self.lake);
+
+            // Unmount the last children first
+            report
+                .unlinked_node_pairs
+                .append(&mut render::unlink_unused_nodes(
+                    &mut self.lake,
+                    detached_children.iter().rev().cloned().collect(),
+                ));
+
+            if let FailureAction::Restart(_) = boundary.action {
+                report.restarts.push(RestartEvent {
+                    supervisor: boundary.boundary_key.clone(),
+                    failure: failure.clone(),
+                    restarted: detached_children,
+                });
+            }
+        }
+
+        let boundary_key = boundary.map(|boundary| boundary.boundary_key);
+        report.failures.push(FailureReport {
+            failure,
+            boundary: boundary_key.clone(),
+        });
+        boundary_key
     }
 
     fn render_node(&mut self, node_key: Key) -> RenderReport {
         let mut report = RenderReport::default();
         let mut next_local_queue = VecDeque::from(vec![node_key]);
         let mut next_global_queue = VecDeque::new();
+        let mut stale_context_readers: Vec<Key> = vec![];
 
         loop {
             let mut now_local_render_queues = VecDeque::new();
             std::mem::swap(&mut next_local_queue, &mut now_local_render_queues);
 
-            if now_local_render_queues.len() == 0 {
+            if now_local_render_queues.is_empty() {
                 break;
             }
 
@@ -175,24 +812,49 @@ impl NodeHost {
 
                 let node_data_point = node_data.borrow_self();
 
-                let RenderResult {
-                    new_nodes,
-                    unused_nodes,
-                    node_control_result,
-                } = render(RenderParam {
+                // Stepping the node now reads the latest contexts
+                stale_context_readers.retain(|reader| reader != &node_key);
+
+                let render_result = render(RenderParam {
                     lake: &mut self.lake,
                     external_render_work_queue: &self.external_render_work_queue,
                     node_key: &node_key,
-                    node_data_point: &&node_data_point,
+                    node_data_point: &node_data_point,
                 });
 
-                link_children_to_lake(&mut self.lake, &node_key, &node_data_point, &new_nodes);
+                let RenderResult {
+                    children,
+                    new_nodes,
+                    unused_nodes,
+                    node_control_result,
+                } = match render_result {
+                    Ok(render_result) => render_result,
+                    Err(cause) => {
+                        drop(node_data_point);
+                        let failure = StepFailure {
+                            key: node_key,
+                            cause,
+                        };
+                        if let Some(boundary_key) = self.handle_failure(failure, &mut report) {
+                            next_local_queue.push_back(boundary_key);
+                        }
+                        return;
+                    }
+                };
+
+                link_children_to_lake(&mut self.lake, &node_key, &node_data_point, &children);
 
                 // Mark pairs as unlinked
                 report
                     .unlinked_node_pairs
                     .append(&mut unlink_unused_nodes(&mut self.lake, unused_nodes));
 
+                // Collect readers of the contexts set by this step
+                append_unique(
+                    &mut stale_context_readers,
+                    self.lake.take_context_rerenders(),
+                );
+
                 // Push rerender to workqueue
                 if node_control_result.rerender_flag {
                     next_global_queue.push_back(WorkItem::Render(node_key.clone()));
@@ -206,8 +868,82 @@ impl NodeHost {
             });
         }
 
-        self.work_queue.append(&mut next_global_queue);
+        // Mount nodes stepped for the first time, children before parents
+        let rendered_keys = report.rendered_keys.clone();
+        rendered_keys.iter().rev().for_each(|node_key| {
+            match lifecycle::mount(&self.lake, node_key) {
+                Ok(Some(node_control_result)) if node_control_result.rerender_flag => {
+                    next_global_queue.push_back(WorkItem::Render(node_key.clone()));
+                }
+                Ok(_) => {}
+                Err(cause) => {
+                    let failure = StepFailure {
+                        key: node_key.clone(),
+                        cause,
+                    };
+                    if let Some(boundary_key) = self.handle_failure(failure, &mut report) {
+                        next_global_queue.push_back(WorkItem::Render(boundary_key));
+                    }
+                }
+            }
+        });
+
+        // Rerender context readers that were not stepped after the context changed,
+        // including readers of contexts set while mounting
+        append_unique(
+            &mut stale_context_readers,
+            self.lake.take_context_rerenders(),
+        );
+        stale_context_readers
+            .into_iter()
+            .filter(|reader| self.lake.get(reader).is_some())
+            .for_each(|reader| next_global_queue.push_back(WorkItem::Render(reader)));
+
+        // Run effects of the committed pass, children before parents
+        rendered_keys.iter().rev().for_each(|node_key| {
+            if let Err(cause) = lifecycle::flush_effects(&self.lake, node_key) {
+                let failure = StepFailure {
+                    key: node_key.clone(),
+                    cause,
+                };
+                if let Some(boundary_key) = self.handle_failure(failure, &mut report) {
+                    next_global_queue.push_back(WorkItem::Render(boundary_key));
+                }
+            }
+        });
+
+        // Panics of nodes that were unmounted or asked to handle a failure
+        report
+            .failures
+            .extend(
+                self.lake
+                    .take_failures()
+                    .into_iter()
+                    .map(|failure| FailureReport {
+                        failure,
+                        boundary: None,
+                    }),
+            );
+
+        next_global_queue
+            .into_iter()
+            .for_each(|work| self.schedule(work));
 
         report
     }
 }
+
+fn append_unique(keys: &mut Vec<Key>, new_keys: Vec<Key>) {
+    new_keys.into_iter().for_each(|key| {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    });
+}
+
+impl Drop for NodeHost {
+    fn drop(&mut self) {
+        // Unmount the whole tree so components release their resources deterministically
+        render::unlink_unused_nodes(&mut self.lake, vec![self.root.clone()]);
+    }
+}
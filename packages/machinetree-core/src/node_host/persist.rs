@@ -0,0 +1,473 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    embeddable::context_holder::ContextHolder,
+    error::MachineTreeError,
+    key::{Key, RawData, RawKey},
+    node::WorkItem,
+    persistence::{
+        path_segment,
+        snapshot::{ContextSnapshot, NodeSnapshot, TreeSnapshot},
+        ComponentRegistry, FileStore,
+    },
+    spec::TreeSpec,
+};
+
+use super::{
+    builder::NodeHostBuilder, failure::StepFailure, lake::NodeLake, lifecycle, render, NodeHost,
+    RenderReport,
+};
+
+impl NodeHost {
+    // Write the tree shape, keys, inputs, component states and contexts to bytes.
+    // Every node must be registered as persistent, unregistered contexts are left out.
+    pub fn snapshot(&self) -> Result<Vec<u8>, MachineTreeError> {
+        Ok(serde_json::to_vec(&self.snapshot_tree()?)?)
+    }
+
+    // Rebuild a host from NodeHost::snapshot, see NodeHostBuilder::restore to configure it
+    pub fn restore(
+        bytes: &[u8],
+        registry: Arc<ComponentRegistry>,
+    ) -> Result<NodeHost, MachineTreeError> {
+        NodeHost::builder().registry(registry).restore(bytes)
+    }
+
+    // Rebuild the tree kept in `dir` by a FileStore after the process stopped, see
+    // NodeHostBuilder::recover to configure the host or the store
+    pub fn recover(
+        dir: impl AsRef<Path>,
+        registry: Arc<ComponentRegistry>,
+    ) -> Result<NodeHost, MachineTreeError> {
+        NodeHost::builder()
+            .registry(registry)
+            .recover(FileStore::open(dir)?)
+    }
+
+    pub(crate) fn snapshot_tree(&self) -> Result<TreeSnapshot, MachineTreeError> {
+        let registry = self.registry.clone().unwrap_or_default();
+        let nodes = render::collect_subtree(&self.lake, self.root.clone())
+            .iter()
+            .map(|node_key| snapshot_node(&self.lake, &registry, node_key))
+            .collect::<Result<_, _>>()?;
+        let root_contexts =
+            snapshot_contexts(&self.lake, &registry, &self.lake.root_context.borrow())?;
+
+        Ok(TreeSnapshot {
+            nodes,
+            root_contexts,
+        })
+    }
+
+    pub(crate) fn from_snapshot(
+        builder: NodeHostBuilder,
+        tree: TreeSnapshot,
+    ) -> Result<NodeHost, MachineTreeError> {
+        let registry = builder.registry.clone().unwrap_or_default();
+        let mut host = NodeHost::with_root(builder, |lake| restore_tree(lake, &registry, tree))?;
+
+        // The nodes were stepped before the snapshot, so they are only mounted, children first
+        for node_key in render::collect_subtree(&host.lake, host.root.clone())
+            .into_iter()
+            .rev()
+        {
+            let mounted = lifecycle::mount(&host.lake, &node_key).map_err(|cause| StepFailure {
+                key: node_key.clone(),
+                cause,
+            })?;
+            if mounted.is_some_and(|node_control_result| node_control_result.rerender_flag) {
+                host.schedule(WorkItem::Render(node_key));
+            }
+        }
+
+        Ok(host)
+    }
+
+    pub(crate) fn from_store(
+        builder: NodeHostBuilder,
+        mut store: FileStore,
+    ) -> Result<NodeHost, MachineTreeError> {
+        let tree = store.read()?.ok_or_else(|| {
+            MachineTreeError::InvalidSnapshot(format!("nothing stored in {:?}", store.dir()))
+        })?;
+        let mut host = NodeHost::from_snapshot(builder, tree)?;
+
+        // Compact the log replayed above
+        store.write_checkpoint(host.snapshot_tree()?)?;
+        host.store = Some(store);
+
+        Ok(host)
+    }
+
+    // Log the nodes a committed pass touched: the rendered nodes, the nodes that received a new
+    // input without stepping, and their ancestors whose contexts gained readers
+    pub(crate) fn commit_pass(&mut self, report: &RenderReport) -> Result<(), MachineTreeError> {
+        let merged_inputs = self.lake.take_merged_inputs();
+        let mut store = match self.store.take() {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        let committed = self.write_pass(&mut store, report, merged_inputs);
+        self.store = Some(store);
+
+        committed
+    }
+
+    fn write_pass(
+        &self,
+        store: &mut FileStore,
+        report: &RenderReport,
+        merged_inputs: Vec<Key>,
+    ) -> Result<(), MachineTreeError> {
+        if store.needs_checkpoint() {
+            return store.write_checkpoint(self.snapshot_tree()?);
+        }
+
+        let registry = self.registry.clone().unwrap_or_default();
+        let mut visited = HashSet::new();
+        let mut touched = vec![];
+        report
+            .rendered_keys
+            .iter()
+            .chain(merged_inputs.iter())
+            .for_each(|node_key| {
+                let mut current = Some(node_key.clone());
+                while let Some(node_key) = current {
+                    if !visited.insert(node_key.clone()) {
+                        break;
+                    }
+                    current = self.lake.parent_of(&node_key);
+                    touched.push(node_key);
+                }
+            });
+
+        let nodes = touched
+            .iter()
+            .filter(|node_key| self.lake.get(node_key).is_some())
+            .map(|node_key| snapshot_node(&self.lake, &registry, node_key))
+            .collect::<Result<_, _>>()?;
+        let unmounted = report
+            .unlinked_node_pairs
+            .iter()
+            .map(|(_, node_data)| node_data.borrow_self().id)
+            .collect();
+        let root_contexts =
+            snapshot_contexts(&self.lake, &registry, &self.lake.root_context.borrow())?;
+
+        store.write_pass(nodes, unmounted, root_contexts)
+    }
+}
+
+fn snapshot_node(
+    lake: &NodeLake,
+    registry: &ComponentRegistry,
+    node_key: &Key,
+) -> Result<NodeSnapshot, MachineTreeError> {
+    let node_data = lake.get(node_key).ok_or(MachineTreeError::MissingNode)?;
+    let node_data_point = node_data.borrow_self();
+    let (type_id, key) = {
+        let node_key_raw = node_key.lock().map_err(|_| MachineTreeError::PoisonedKey)?;
+        (node_key_raw.type_id, node_key_raw.key.clone())
+    };
+    let entry = registry.component_by_type(type_id).ok_or_else(|| {
+        MachineTreeError::UnregisteredComponent {
+            path: node_path(lake, registry, node_key),
+        }
+    })?;
+
+    // Hook state is kept by type only, it could not be read back
+    if !node_data_point.borrow_mut_state().is_empty() {
+        return Err(MachineTreeError::NotPersistent {
+            path: node_path(lake, registry, node_key),
+            reason: "holds state from NodeControl::use_state".to_string(),
+        });
+    }
+
+    let (input, state, declared_children) = {
+        let node_data_borrow = node_data_point.borrow_data();
+        let component = node_data_borrow.component.borrow();
+        let (input, state) = (entry.save)(component.component_any(), &node_data_borrow.input)?;
+        let declared_children = node_data_borrow
+            .declared_children
+            .iter()
+            .map(|declared| {
+                // Only seeds of the registry can be seeded again on restore
+                TreeSpec::from_seed(declared).ok_or_else(|| {
+                    MachineTreeError::UnregisteredComponent {
+                        path: format!(
+                            "{}{}",
+                            node_path(lake, registry, node_key),
+                            path_segment("<declared>", &declared.key.key)
+                        ),
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (input, state, declared_children)
+    };
+
+    let children = node_data_point
+        .borrow_relations()
+        .children
+        .iter()
+        .filter_map(|child| Key::try_from(child).ok())
+        .filter_map(|child| lake.id_of(&child))
+        .collect();
+    let contexts = snapshot_contexts(lake, registry, &node_data_point.borrow_context())?;
+
+    Ok(NodeSnapshot {
+        id: node_data_point.id,
+        parent: lake
+            .parent_of(node_key)
+            .and_then(|parent| lake.id_of(&parent)),
+        children,
+        component: entry.name.clone(),
+        key,
+        input,
+        version: entry.version,
+        state,
+        priority: node_data_point.priority.get(),
+        contexts,
+        declared_children,
+    })
+}
+
+// Sorted by name, so that equal trees give equal snapshots
+fn snapshot_contexts(
+    lake: &NodeLake,
+    registry: &ComponentRegistry,
+    context_holder: &ContextHolder,
+) -> Result<Vec<ContextSnapshot>, MachineTreeError> {
+    let mut contexts = context_holder
+        .type_map
+        .iter()
+        .filter_map(|(type_id, value)| Some((registry.context_by_type(*type_id)?, value)))
+        .map(|(entry, value)| {
+            let subscribers = context_holder
+                .subscribers
+                .get(&entry.type_id)
+                .map(|readers| {
+                    readers
+                        .iter()
+                        .filter_map(|reader| Key::try_from(reader).ok())
+                        .filter_map(|reader| lake.id_of(&reader))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(ContextSnapshot {
+                name: entry.name.clone(),
+                value: (entry.save)(value.as_ref())?,
+                subscribers,
+            })
+        })
+        .collect::<Result<Vec<_>, MachineTreeError>>()?;
+    contexts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(contexts)
+}
+
+fn restore_tree(
+    lake: &mut NodeLake,
+    registry: &ComponentRegistry,
+    tree: TreeSnapshot,
+) -> Result<Key, MachineTreeError> {
+    let TreeSnapshot {
+        nodes,
+        root_contexts,
+    } = tree;
+    let mut keys: HashMap<u64, (Key, String)> = HashMap::new();
+    let mut contexts = vec![];
+    let mut root = None;
+
+    for node in nodes {
+        let NodeSnapshot {
+            id,
+            parent,
+            component,
+            key,
+            children: _,
+            input,
+            version,
+            state,
+            priority,
+            contexts: node_contexts,
+            declared_children,
+        } = node;
+
+        if keys.contains_key(&id) {
+            return Err(MachineTreeError::InvalidSnapshot(format!(
+                "node id {} is used twice",
+                id
+            )));
+        }
+        let parent = match parent {
+            Some(parent) => Some(keys.get(&parent).cloned().ok_or_else(|| {
+                MachineTreeError::InvalidSnapshot(format!(
+                    "node {} is listed before its parent {}",
+                    id, parent
+                ))
+            })?),
+            None => None,
+        };
+        let path = format!(
+            "{}{}",
+            parent.as_ref().map_or("", |(_, path)| path.as_str()),
+            path_segment(&component, &key)
+        );
+
+        let entry = registry
+            .component_by_name(&component)
+            .ok_or_else(|| MachineTreeError::UnregisteredComponent { path: path.clone() })?;
+        let state = registry.migrate(entry, version, state, &path)?;
+        let (input, component) =
+            (entry.restore)(input, state).map_err(|error| MachineTreeError::RestoreFailed {
+                path: path.clone(),
+                reason: error.to_string(),
+            })?;
+        let declared_children = declared_children
+            .iter()
+            .map(|declared| declared.seed(registry))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| {
+                MachineTreeError::InvalidSnapshot(format!(
+                    "declared children of {}: {}",
+                    path, error
+                ))
+            })?;
+        let (node_key, node_data) = lake.insert_raw(
+            id,
+            RawKey {
+                type_id: entry.type_id,
+                key,
+                self_render: Default::default(),
+                detached: false,
+            },
+            RawData {
+                input,
+                previous_input: None,
+                declared_children,
+                component: Box::new(RefCell::new(component)),
+                mounted: false,
+            },
+        );
+        let node_data_point = node_data.borrow_self();
+        node_data_point.priority.set(priority);
+
+        match parent {
+            Some((parent_key, _)) => {
+                node_data_point.borrow_mut_relations().parent = Some((&parent_key).into());
+                if let Some(parent_data) = lake.get(&parent_key) {
+                    parent_data
+                        .borrow_self()
+                        .borrow_mut_relations()
+                        .children
+                        .push((&node_key).into());
+                }
+            }
+            None if root.is_none() => root = Some(node_key.clone()),
+            None => {
+                return Err(MachineTreeError::InvalidSnapshot(format!(
+                    "{} is a second root",
+                    path
+                )))
+            }
+        }
+
+        contexts.push((node_key.clone(), path.clone(), node_contexts));
+        keys.insert(id, (node_key, path));
+    }
+
+    let root = root.ok_or_else(|| MachineTreeError::InvalidSnapshot("no root".to_string()))?;
+
+    // Subscribers may be listed before they are restored, so contexts are restored last
+    let ids: HashMap<u64, Key> = keys
+        .iter()
+        .map(|(id, (node_key, _))| (*id, node_key.clone()))
+        .collect();
+    restore_contexts(
+        registry,
+        &ids,
+        &mut lake.root_context.borrow_mut(),
+        root_contexts,
+        "/",
+    )?;
+    for (node_key, path, node_contexts) in contexts {
+        let node_data = lake.get(&node_key).ok_or(MachineTreeError::MissingNode)?;
+        restore_contexts(
+            registry,
+            &ids,
+            &mut node_data.borrow_self().borrow_mut_context(),
+            node_contexts,
+            &path,
+        )?;
+    }
+
+    Ok(root)
+}
+
+fn restore_contexts(
+    registry: &ComponentRegistry,
+    ids: &HashMap<u64, Key>,
+    context_holder: &mut ContextHolder,
+    contexts: Vec<ContextSnapshot>,
+    path: &str,
+) -> Result<(), MachineTreeError> {
+    contexts.into_iter().try_for_each(|context| {
+        let ContextSnapshot {
+            name,
+            value,
+            subscribers,
+        } = context;
+        let entry = registry.context_by_name(&name).ok_or_else(|| {
+            MachineTreeError::UnregisteredContext {
+                name: name.clone(),
+                path: path.to_string(),
+            }
+        })?;
+
+        let value = (entry.restore)(value).map_err(|error| MachineTreeError::RestoreFailed {
+            path: format!("context {:?} of {}", name, path),
+            reason: error.to_string(),
+        })?;
+        context_holder.type_map.insert(entry.type_id, value);
+        subscribers
+            .iter()
+            .filter_map(|id| ids.get(id))
+            .for_each(|reader| context_holder.subscribe_type(entry.type_id, reader));
+
+        Ok(())
+    })
+}
+
+// Path of registered names and keys from the root, for error messages
+pub(crate) fn node_path(lake: &NodeLake, registry: &ComponentRegistry, node_key: &Key) -> String {
+    let mut segments = vec![];
+    let mut current = Some(node_key.clone());
+
+    while let Some(node_key) = current {
+        let segment = node_key.lock().ok().map(|node_key_raw| {
+            let name = registry
+                .component_by_type(node_key_raw.type_id)
+                .map(|entry| entry.name.clone())
+                .or_else(|| {
+                    let node_data = lake.get(&node_key)?;
+                    let node_data_point = node_data.borrow_self();
+                    let node_data_borrow = node_data_point.borrow_data();
+                    let type_name = node_data_borrow.component.borrow().type_name();
+                    Some(type_name.to_string())
+                })
+                .unwrap_or_default();
+            path_segment(&name, &node_key_raw.key)
+        });
+        segments.push(segment.unwrap_or_default());
+        current = lake.parent_of(&node_key);
+    }
+
+    segments.into_iter().rev().collect()
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::{
+    error::MachineTreeError, node::WorkItem, persistence::ComponentRegistry, spec::TreeSpec,
+};
+
+use super::{builder::NodeHostBuilder, render, NodeHost};
+
+impl NodeHostBuilder {
+    // Root the host at the tree described by `spec`, with the components of the registry
+    pub fn spec(self, spec: &TreeSpec) -> Result<NodeHost, MachineTreeError> {
+        let registry = self.registry.clone().unwrap_or_default();
+        let seed = spec.seed(&registry)?;
+        Ok(self.root(seed))
+    }
+}
+
+impl NodeHost {
+    pub fn from_spec(
+        spec: &TreeSpec,
+        registry: Arc<ComponentRegistry>,
+    ) -> Result<NodeHost, MachineTreeError> {
+        NodeHost::builder().registry(registry).spec(spec)
+    }
+
+    // Reconcile the live tree with a changed spec, as if the root's parent stepped it again:
+    // nodes keeping their component and key receive their new input, the others are mounted
+    // or unmounted. Nothing changes if the spec is invalid. Fails if the new input of a root
+    // that skips its step cannot be written to the host's FileStore.
+    pub fn reload(&mut self, spec: &TreeSpec) -> Result<(), MachineTreeError> {
+        let registry = self.registry.clone().unwrap_or_default();
+        let seed = spec.seed(&registry)?;
+
+        let same_root = self
+            .root
+            .lock()
+            .map(|root| root.type_id == seed.key.type_id && root.key == seed.key.key)
+            .map_err(|_| MachineTreeError::PoisonedKey)?;
+        if !same_root {
+            return Err(MachineTreeError::InvalidSpec {
+                path: "$".to_string(),
+                reason: "the component and key of the root cannot change".to_string(),
+            });
+        }
+
+        match render::merge_seed_to_nodekey(&mut self.lake, &seed, &self.root)? {
+            true => self.schedule(WorkItem::Render(self.root.clone())),
+            // No pass is coming to log the new input of the root
+            false => self.commit_pass(&Default::default())?,
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,17 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+};
 
-use crate::key::{Key, Seed};
+use crate::{
+    error::MachineTreeError,
+    key::{Key, Seed},
+};
 
 use super::{
+    failure::{FailureCause, StepFailure},
     lake::{NodeData, NodeDataPoint, NodeLake},
-    ExternalRenderWorkQueue, NodeControl, NodeControlResult,
+    lifecycle, ExternalRenderWorkQueue, NodeControl, NodeControlResult,
 };
 
 pub(crate) struct RenderParam<'a> {
@@ -15,12 +22,14 @@ pub(crate) struct RenderParam<'a> {
 }
 
 pub(crate) struct RenderResult {
+    pub(crate) children: Vec<Key>,
     pub(crate) new_nodes: Vec<Key>,
     pub(crate) unused_nodes: Vec<Key>,
     pub(crate) node_control_result: NodeControlResult,
 }
 
-pub(crate) fn render(param: RenderParam) -> RenderResult {
+// Fails without touching the node's children if the step panicked or returned an Err
+pub(crate) fn render(param: RenderParam) -> Result<RenderResult, FailureCause> {
     let RenderParam {
         lake,
         external_render_work_queue,
@@ -30,14 +39,15 @@ pub(crate) fn render(param: RenderParam) -> RenderResult {
 
     let StepResult {
         new_seeds,
-        node_control_result,
+        mut node_control_result,
     } = run_step_fn(StepParam {
         lake,
         node_key,
         node_data_point,
-    });
+    })?;
 
     let ReconciliationResult {
+        children,
         new_nodes,
         unused_nodes,
     } = reconcile(ReconciliationParam {
@@ -45,13 +55,18 @@ pub(crate) fn render(param: RenderParam) -> RenderResult {
         external_render_work_queue,
         node_data_point,
         new_seeds,
-    });
+    })?;
 
-    RenderResult {
+    node_data_point
+        .borrow_mut_effects()
+        .merge(std::mem::take(&mut node_control_result.effects));
+
+    Ok(RenderResult {
+        children,
         new_nodes,
         unused_nodes,
         node_control_result,
-    }
+    })
 }
 
 struct StepParam<'a> {
@@ -64,27 +79,58 @@ struct StepResult {
     pub(crate) node_control_result: NodeControlResult,
 }
 
-fn run_step_fn(param: StepParam) -> StepResult {
+fn run_step_fn(param: StepParam) -> Result<StepResult, FailureCause> {
     let StepParam {
         lake,
         node_key,
         node_data_point,
     } = param;
-    let node_data_borrow = node_data_point.borrow_data_mut();
-    let mut step_fn_borrow = node_data_borrow.step_fn.borrow_mut();
+    let mut node_data_borrow = node_data_point.borrow_data_mut();
+    let previous_input = node_data_borrow.previous_input.take();
+
+    let stepped = {
+        let node_data_ref = &*node_data_borrow;
+        let mut component_borrow = node_data_ref.component.borrow_mut();
+
+        // Catch panics here, so that the borrows above are released in order and the host
+        // remains usable
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            // Deliver queued messages before stepping, with a control of their own on which
+            // hooks are refused
+            let messages = node_data_point.take_messages();
+            let messages_rerender_flag =
+                messages.into_iter().fold(false, |rerender_flag, message| {
+                    let mut control = NodeControl::new(lake, node_key.clone());
+                    component_borrow.on_message(&mut control, message);
+                    rerender_flag || control.rerender_flag
+                });
 
-    let mut control = NodeControl {
-        rerender_flag: false,
-        current: node_key.clone(),
-        lake: &lake,
-    };
+            let mut control = NodeControl::for_step(lake, node_key.clone());
+            control.rerender_flag = messages_rerender_flag;
+            control.previous_input = previous_input.as_ref();
 
-    let produced_nodes = (step_fn_borrow)(&mut control, &node_data_borrow.input);
-    let node_control_result: NodeControlResult = control.into();
+            let produced_nodes = component_borrow.try_step(&mut control, &node_data_ref.input);
+            produced_nodes.map(|mut new_seeds| {
+                new_seeds.extend(node_data_ref.declared_children.iter().cloned());
+                StepResult {
+                    new_seeds,
+                    node_control_result: control.into(),
+                }
+            })
+        }))
+    };
 
-    StepResult {
-        new_seeds: produced_nodes,
-        node_control_result,
+    match stepped {
+        Ok(Ok(step_result)) => Ok(step_result),
+        Ok(Err(error)) => {
+            // The failed step did not see its input, the next one compares against the same
+            node_data_borrow.previous_input = previous_input;
+            Err(error.into())
+        }
+        Err(payload) => {
+            node_data_borrow.previous_input = previous_input;
+            Err(FailureCause::from_panic(payload))
+        }
     }
 }
 
@@ -96,86 +142,90 @@ struct ReconciliationParam<'a> {
 }
 
 struct ReconciliationResult {
+    // Every child in order, to be linked to the parent
+    pub(crate) children: Vec<Key>,
+    // Children that need to be stepped
     pub(crate) new_nodes: Vec<Key>,
     pub(crate) unused_nodes: Vec<Key>,
 }
 
-fn reconcile<'a>(
+// Fails if should_step or a constructor of a child panics, the step that returned the seeds
+// then fails and the nodes sprouted so far are removed
+fn reconcile(
     ReconciliationParam {
         lake,
         external_render_work_queue,
         node_data_point,
         new_seeds,
     }: ReconciliationParam,
-) -> ReconciliationResult {
+) -> Result<ReconciliationResult, FailureCause> {
     let children = &mut node_data_point.borrow_mut_relations().children;
 
-    let supposed_new_order: HashMap<_, _> = new_seeds.iter().map(|x| (&x.key, &x)).collect();
-
-    // Set aside keyed
-
-    // Inquire trashed nodes
-    let mut unused_node_keys: HashSet<Key> = {
+    // Inquire trashed nodes, in child order so that they are unmounted in a reproducible order
+    let mut unused_node_keys: Vec<Key> = {
         // HashMap mapping new seeds by its key
-        let new_seed_lookup_map: HashMap<&String, &Seed> =
+        let new_seed_lookup_map: HashMap<&Option<String>, &Seed> =
             new_seeds.iter().map(|seed| (&seed.key.key, seed)).collect();
 
         let unused_nodes = children
             .iter()
-            .filter_map(|child| child.upgrade())
-            .filter_map(|child| child.lock().ok())
-            .filter_map(|child| -> Option<Key> {
-                let is_a_match = child.lock().map_or(false, |child_key| {
+            .filter_map(|child| -> Option<Key> { child.try_into().ok() })
+            .filter(|child| {
+                let is_a_match = child.lock().is_ok_and(|child_key| {
                     new_seed_lookup_map
                         .get(&child_key.key)
-                        .map_or(false, |new_seed| new_seed.key.type_id == child_key.type_id)
+                        .is_some_and(|new_seed| new_seed.key.type_id == child_key.type_id)
                 });
 
-                if !is_a_match {
-                    Some(child.into())
-                } else {
-                    None
-                }
+                !is_a_match
             })
-            .collect::<HashSet<_>>();
+            .collect::<Vec<_>>();
 
         unused_nodes
     };
 
-    let new_node_keys: Vec<Key> = {
-        let mut old_children_lookup_map: HashMap<String, Key> = children
+    let mut sprouted: Vec<Key> = vec![];
+    let child_keys: Result<Vec<(Key, bool)>, FailureCause> = {
+        let mut old_children_lookup_map: HashMap<Option<String>, Key> = children
             .iter()
             .filter_map(|child| -> Option<Key> { child.try_into().ok() })
-            .filter_map(|child| -> Option<(String, Key)> {
+            .filter_map(|child| -> Option<(Option<String>, Key)> {
                 let key = child.0.try_lock().ok()?.key.clone();
-                Some((key, child.into()))
+                Some((key, child))
             })
             .collect();
 
         new_seeds
             .into_iter()
-            .map(|new_seed| -> Key {
+            .map(|new_seed| -> Result<(Key, bool), FailureCause> {
                 let mut unused_old_key_opt = None;
 
                 // Find old key, reuse if possible, mark as unused if not
                 if let Some(old_key) = old_children_lookup_map.remove(&new_seed.key.key) {
-                    if let Ok(_) = merge_seed_to_nodekey(lake, &new_seed, &old_key) {
-                        // Old_child is reusable
-                        return old_key;
+                    match merge_seed_to_nodekey(lake, &new_seed, &old_key) {
+                        // Old_child is reusable, and is skipped if its component says so
+                        Ok(should_step) => return Ok((old_key, should_step)),
+                        Err(MachineTreeError::StepFailure(failure)) => return Err(failure.cause),
+                        // Set trashed_old_child_arc
+                        Err(_) => unused_old_key_opt = Some(old_key),
                     }
-
-                    // Set trashed_old_child_arc
-                    unused_old_key_opt = Some(old_key);
                 }
 
                 // Past this point, use new seed to create a new node
 
                 if let Some(unused_old_key) = unused_old_key_opt {
-                    unused_node_keys.insert(unused_old_key.clone());
+                    if !unused_node_keys.contains(&unused_old_key) {
+                        unused_node_keys.push(unused_old_key);
+                    }
                 }
 
                 // Consume seed into lake, and get the linked nodekey
-                let (node_key, _) = lake.sprout_and_link(new_seed);
+                let (raw_key, raw_data) =
+                    panic::catch_unwind(AssertUnwindSafe(|| new_seed.sprout()))
+                        .map_err(FailureCause::from_panic)?;
+                let id = lake.next_node_id;
+                let (node_key, _) = lake.insert_raw(id, raw_key, raw_data);
+                sprouted.push(node_key.clone());
 
                 // TODO: handle deadlock
                 // Set self-signal on a new node_key
@@ -186,42 +236,89 @@ fn reconcile<'a>(
                     );
                 };
 
-                node_key
+                Ok((node_key, true))
             })
             .collect()
     };
 
-    ReconciliationResult {
-        new_nodes: new_node_keys,
-        unused_nodes: unused_node_keys.into_iter().collect(),
-    }
+    let child_keys = match child_keys {
+        Ok(child_keys) => child_keys,
+        Err(cause) => {
+            sprouted.iter().for_each(|child_key| {
+                lake.remove(child_key);
+                if let Ok(mut child_key_raw) = child_key.lock() {
+                    child_key_raw.detached = true;
+                }
+            });
+            return Err(cause);
+        }
+    };
+
+    Ok(ReconciliationResult {
+        new_nodes: child_keys
+            .iter()
+            .filter(|(_, should_step)| *should_step)
+            .map(|(child_key, _)| child_key.clone())
+            .collect(),
+        children: child_keys
+            .into_iter()
+            .map(|(child_key, _)| child_key)
+            .collect(),
+        unused_nodes: unused_node_keys,
+    })
 }
 
-fn merge_seed_to_nodekey(lake: &mut NodeLake, new_seed: &Seed, node_key: &Key) -> Result<(), ()> {
+// Reuse the node for the new seed, resolving whether the node needs to be stepped again
+pub(crate) fn merge_seed_to_nodekey(
+    lake: &mut NodeLake,
+    new_seed: &Seed,
+    node_key: &Key,
+) -> Result<bool, MachineTreeError> {
     // TODO: handle deadlocks
     // Don't merge if node_key.lock() fails
-    let old_child_handle = node_key.lock().map_err(|_| Default::default())?;
+    let old_child_handle = node_key.lock().map_err(|_| MachineTreeError::PoisonedKey)?;
 
     // Don't if type_id is different
     if old_child_handle.type_id != new_seed.key.type_id {
-        return Err(Default::default());
+        return Err(MachineTreeError::TypeMismatch);
     }
 
     // Don't merge if lake.get fails
-    let node_data = lake.get(&node_key).ok_or_else(|| Default::default())?;
+    let node_data = lake.get(node_key).ok_or(MachineTreeError::MissingNode)?;
 
     let node_data_point = node_data.borrow_self();
     let mut node_raw_data = node_data_point.borrow_data_mut();
-    node_raw_data.input = new_seed.clone_input();
+    let new_input = new_seed.clone_input();
+    // Declared children are not compared, nodes that have some always step to reconcile them
+    let declared_children = new_seed.data.declared_children.clone();
+    let should_step = !declared_children.is_empty()
+        || !node_raw_data.declared_children.is_empty()
+        || panic::catch_unwind(AssertUnwindSafe(|| {
+            node_raw_data
+                .component
+                .borrow()
+                .should_step(&node_raw_data.input, &new_input)
+        }))
+        .map_err(|payload| StepFailure {
+            key: node_key.clone(),
+            cause: FailureCause::from_panic(payload),
+        })?;
+    node_raw_data.declared_children = declared_children;
+    let old_input = std::mem::replace(&mut node_raw_data.input, new_input);
+    // Skipped steps keep the input the last step actually saw
+    if node_raw_data.previous_input.is_none() {
+        node_raw_data.previous_input = Some(old_input);
+    }
+    lake.push_merged_input(node_key.clone());
 
-    return Ok(());
+    Ok(should_step)
 }
 
 pub(crate) fn link_children_to_lake<'a>(
     lake: &'a mut NodeLake,
     node_key: &Key,
     node_data_point: &'a NodeDataPoint,
-    new_nodes: &Vec<Key>,
+    new_nodes: &[Key],
 ) {
     // Assign children weak nodes
     node_data_point.borrow_mut_relations().children =
@@ -229,19 +326,19 @@ pub(crate) fn link_children_to_lake<'a>(
 
     // Set children's parent to node_key
     new_nodes.iter().for_each(|child_key| {
-        lake.get(child_key).map(|child_data_pointer| {
+        if let Some(child_data_pointer) = lake.get(child_key) {
             child_data_pointer
                 .borrow_self()
                 .borrow_mut_relations()
                 .parent = Some(node_key.into())
-        });
+        }
     });
 }
 
 pub type UnlinkedPair = (Key, NodeData);
 
-pub(crate) fn unlink_unused_nodes<'a>(
-    lake: &'a mut NodeLake,
+pub(crate) fn unlink_unused_nodes(
+    lake: &mut NodeLake,
     unused_nodes: Vec<Key>,
 ) -> Vec<UnlinkedPair> {
     unused_nodes
@@ -253,33 +350,49 @@ pub(crate) fn unlink_unused_nodes<'a>(
         })
 }
 
-fn unlink_recursively<'a>(
-    lake: &'a mut NodeLake,
-    into_nodeshell: impl TryInto<Key>,
-) -> Vec<UnlinkedPair> {
+fn unlink_recursively(lake: &mut NodeLake, into_nodeshell: impl TryInto<Key>) -> Vec<UnlinkedPair> {
     into_nodeshell
         .try_into()
         .map_or(Default::default(), |node_key| {
-            lake.remove(&node_key)
-                .map_or(Default::default(), |removed| {
-                    let removed_children = removed
-                        .borrow_self()
-                        .borrow_relations()
-                        .children
-                        .iter()
-                        .filter_map(|node_key_weak| node_key_weak.upgrade())
-                        .map(|child_key_raw| unlink_recursively(lake, child_key_raw))
-                        .collect::<Vec<_>>();
-
-                    let unlinked_pairs = removed_children.into_iter().fold(
-                        vec![(node_key.clone(), removed)],
-                        |mut all_unlinked_pairs, mut unlinked_pairs| {
-                            all_unlinked_pairs.append(&mut unlinked_pairs);
-                            all_unlinked_pairs
-                        },
-                    );
-
-                    unlinked_pairs
+            let subtree = collect_subtree(lake, node_key);
+
+            // Unmount while the subtree is still in the lake so that contexts remain reachable.
+            // Parents precede their descendants in `subtree`, so reversing it unmounts children first.
+            subtree
+                .iter()
+                .rev()
+                .for_each(|node_key| lifecycle::unmount(lake, node_key));
+
+            subtree
+                .into_iter()
+                .filter_map(|node_key| {
+                    let removed = lake.remove(&node_key)?;
+                    if let Ok(mut node_key_raw) = node_key.lock() {
+                        node_key_raw.detached = true;
+                    }
+                    Some((node_key, removed))
                 })
+                .collect()
+        })
+}
+
+// Pre-order listing of the node and its descendants that are present in the lake
+pub(crate) fn collect_subtree(lake: &NodeLake, node_key: Key) -> Vec<Key> {
+    let children = match lake.get(&node_key) {
+        Some(node_data) => node_data
+            .borrow_self()
+            .borrow_relations()
+            .children
+            .iter()
+            .filter_map(|node_key_weak| -> Option<Key> { node_key_weak.try_into().ok() })
+            .collect::<Vec<_>>(),
+        None => return vec![],
+    };
+
+    children
+        .into_iter()
+        .fold(vec![node_key], |mut subtree, child_key| {
+            subtree.append(&mut collect_subtree(lake, child_key));
+            subtree
         })
 }
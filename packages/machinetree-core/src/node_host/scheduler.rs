@@ -0,0 +1,233 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, VecDeque},
+};
+
+use crate::node::WorkItem;
+
+use super::simulation::SimRng;
+
+// Information the host knows about a node at the time its work is enqueued
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkInfo {
+    // Distance from the root, the root being 0
+    pub depth: usize,
+    // Set by the node through NodeControl::set_priority, defaults to 0
+    pub priority: i32,
+}
+
+// Decides which WorkItem NodeHost::render picks next.
+// Every WorkItem enqueued by the host goes through `push`.
+pub trait Scheduler {
+    fn push(&mut self, item: WorkItem, info: WorkInfo);
+
+    fn pop(&mut self) -> Option<WorkItem>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// First in, first out
+#[derive(Default)]
+pub struct FifoScheduler {
+    queue: VecDeque<WorkItem>,
+}
+
+impl Scheduler for FifoScheduler {
+    fn push(&mut self, item: WorkItem, _: WorkInfo) {
+        self.queue.push_back(item);
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        self.queue.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// Last in, first out
+#[derive(Default)]
+pub struct LifoScheduler {
+    stack: Vec<WorkItem>,
+}
+
+impl Scheduler for LifoScheduler {
+    fn push(&mut self, item: WorkItem, _: WorkInfo) {
+        self.stack.push(item);
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        self.stack.pop()
+    }
+
+    fn len(&self) -> usize {
+        self.stack.len()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthOrder {
+    ShallowestFirst,
+    DeepestFirst,
+}
+
+// Orders work by the tree depth of the node, ties are resolved first in, first out
+pub struct TreeDepthScheduler {
+    order: DepthOrder,
+    queue: RankedQueue<i64>,
+}
+
+impl TreeDepthScheduler {
+    pub fn new(order: DepthOrder) -> Self {
+        Self {
+            order,
+            queue: Default::default(),
+        }
+    }
+
+    pub fn shallowest_first() -> Self {
+        Self::new(DepthOrder::ShallowestFirst)
+    }
+
+    pub fn deepest_first() -> Self {
+        Self::new(DepthOrder::DeepestFirst)
+    }
+}
+
+impl Scheduler for TreeDepthScheduler {
+    fn push(&mut self, item: WorkItem, info: WorkInfo) {
+        let rank = match self.order {
+            DepthOrder::ShallowestFirst => -(info.depth as i64),
+            DepthOrder::DeepestFirst => info.depth as i64,
+        };
+        self.queue.push(rank, item);
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        self.queue.pop()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// Highest priority first, ties are resolved first in, first out
+#[derive(Default)]
+pub struct PriorityScheduler {
+    queue: RankedQueue<i32>,
+}
+
+impl Scheduler for PriorityScheduler {
+    fn push(&mut self, item: WorkItem, info: WorkInfo) {
+        self.queue.push(info.priority, item);
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        self.queue.pop()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// Picks any pending work, reproducibly from its seed, see NodeHostBuilder::simulation
+pub struct RandomScheduler {
+    rng: SimRng,
+    items: Vec<WorkItem>,
+}
+
+impl RandomScheduler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SimRng::new(seed),
+            items: vec![],
+        }
+    }
+}
+
+impl Scheduler for RandomScheduler {
+    fn push(&mut self, item: WorkItem, _: WorkInfo) {
+        self.items.push(item);
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let index = self.rng.below(self.items.len());
+        Some(self.items.swap_remove(index))
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+// Max-heap by rank, insertion order breaks ties
+struct RankedQueue<Rank: Ord> {
+    heap: BinaryHeap<RankedEntry<Rank>>,
+    sequence: u64,
+}
+
+impl<Rank: Ord> Default for RankedQueue<Rank> {
+    fn default() -> Self {
+        Self {
+            heap: Default::default(),
+            sequence: 0,
+        }
+    }
+}
+
+impl<Rank: Ord> RankedQueue<Rank> {
+    fn push(&mut self, rank: Rank, item: WorkItem) {
+        self.heap.push(RankedEntry {
+            rank,
+            sequence: Reverse(self.sequence),
+            item,
+        });
+        self.sequence += 1;
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        self.heap.pop().map(|entry| entry.item)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+struct RankedEntry<Rank: Ord> {
+    rank: Rank,
+    sequence: Reverse<u64>,
+    item: WorkItem,
+}
+
+impl<Rank: Ord> PartialEq for RankedEntry<Rank> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Rank: Ord> Eq for RankedEntry<Rank> {}
+
+impl<Rank: Ord> PartialOrd for RankedEntry<Rank> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Rank: Ord> Ord for RankedEntry<Rank> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank
+            .cmp(&other.rank)
+            .then_with(|| self.sequence.cmp(&other.sequence))
+    }
+}
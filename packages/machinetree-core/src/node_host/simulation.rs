@@ -0,0 +1,94 @@
+use crate::node::ExternalWorkItem;
+
+// SplitMix64, small and stable across platforms and releases so that a seed always replays
+// the same run
+#[derive(Clone, Debug)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..bound, bound must not be 0
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+// State of a host built with NodeHostBuilder::simulation
+pub(crate) struct Simulation {
+    pub(crate) seed: u64,
+    rng: SimRng,
+    // External work held back for a later poll
+    deferred: Vec<ExternalWorkItem>,
+}
+
+impl Simulation {
+    pub(crate) fn new(seed: u64, rng: SimRng) -> Self {
+        Self {
+            seed,
+            rng,
+            deferred: vec![],
+        }
+    }
+
+    pub(crate) fn has_deferred_work(&self) -> bool {
+        !self.deferred.is_empty()
+    }
+
+    // Interleave the received work with the work held back at random, keeping the order of
+    // the work sent to each node, and hold back a random part of it
+    pub(crate) fn interleave(&mut self, received: Vec<ExternalWorkItem>) -> Vec<ExternalWorkItem> {
+        let mut queues: Vec<Vec<ExternalWorkItem>> = vec![];
+        let mut queue_keys: Vec<Option<usize>> = vec![];
+
+        self.deferred.drain(..).chain(received).for_each(|work| {
+            let key = match &work {
+                ExternalWorkItem::Render(key) | ExternalWorkItem::Message(key, _) => {
+                    Some(key.read_ptr_as_usize())
+                }
+                ExternalWorkItem::Shutdown => None,
+            };
+            match queue_keys
+                .iter()
+                .position(|queue_key| key.is_some() && *queue_key == key)
+            {
+                Some(index) => queues[index].push(work),
+                None => {
+                    queue_keys.push(key);
+                    queues.push(vec![work]);
+                }
+            }
+        });
+
+        queues.iter_mut().for_each(|queue| queue.reverse());
+        let mut interleaved = vec![];
+        while !queues.is_empty() {
+            let index = self.rng.below(queues.len());
+            if let Some(work) = queues[index].pop() {
+                interleaved.push(work);
+            }
+            if queues[index].is_empty() {
+                queues.remove(index);
+            }
+        }
+
+        if !interleaved.is_empty() {
+            let accepted = 1 + self.rng.below(interleaved.len());
+            self.deferred = interleaved.split_off(accepted);
+        }
+
+        interleaved
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    time::{Duration, Instant},
+};
+
+use crate::key::Key;
+
+// Shortest period of NodeControl::set_interval
+pub const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+// Identifies a timer set through NodeControl::set_timeout or NodeControl::set_interval
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+// Pending timers of the tree, earliest deadline first
+#[derive(Default)]
+pub(crate) struct TimerQueue {
+    heap: BinaryHeap<TimerEntry>,
+    next_id: u64,
+}
+
+impl TimerQueue {
+    pub(crate) fn insert(
+        &mut self,
+        node_key: Key,
+        deadline: Instant,
+        interval: Option<Duration>,
+    ) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.heap.push(TimerEntry {
+            deadline,
+            id,
+            node_key,
+            interval,
+        });
+        id
+    }
+
+    pub(crate) fn cancel(&mut self, id: TimerId) {
+        self.heap.retain(|entry| entry.id != id);
+    }
+
+    pub(crate) fn cancel_node(&mut self, node_key: &Key) {
+        self.heap.retain(|entry| &entry.node_key != node_key);
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.heap.peek().map(|entry| entry.deadline)
+    }
+
+    // Remove the timers due at `now`, returning their nodes in deadline order.
+    // Intervals are put back with their next deadline, skipping the periods that were missed.
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Vec<Key> {
+        let mut due_nodes = vec![];
+        let mut intervals = vec![];
+
+        while self.heap.peek().is_some_and(|entry| entry.deadline <= now) {
+            let Some(mut entry) = self.heap.pop() else {
+                break;
+            };
+            due_nodes.push(entry.node_key.clone());
+
+            if let Some(interval) = entry.interval {
+                entry.deadline += interval;
+                if entry.deadline <= now {
+                    entry.deadline = now + interval;
+                }
+                intervals.push(entry);
+            }
+        }
+
+        self.heap.extend(intervals);
+        due_nodes
+    }
+}
+
+struct TimerEntry {
+    deadline: Instant,
+    id: TimerId,
+    node_key: Key,
+    interval: Option<Duration>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, as BinaryHeap is a max-heap
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Reverse(self.deadline)
+            .cmp(&Reverse(other.deadline))
+            .then_with(|| Reverse(self.id.0).cmp(&Reverse(other.id.0)))
+    }
+}
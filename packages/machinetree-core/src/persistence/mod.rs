@@ -0,0 +1,40 @@
+mod registry;
+pub(crate) mod snapshot;
+mod store;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::node::Component;
+
+pub use self::{
+    registry::{ComponentRegistry, MigrationError},
+    store::FileStore,
+};
+
+// A Component whose state survives NodeHost::snapshot and NodeHost::restore.
+// Effects, timers and pending messages are not persisted, restored nodes are mounted again
+// without being stepped, so re-arm timers from on_mount. Keep state in the component rather
+// than through NodeControl::use_state: snapshots of nodes holding hook state fail with
+// MachineTreeError::NotPersistent.
+// ErrorBoundary, Supervisor and AsyncNode cannot be persisted, their inputs hold seeds and
+// closures and AsyncNode a running future, so snapshots of trees holding one fail with
+// MachineTreeError::UnregisteredComponent.
+pub trait PersistentComponent: Component {
+    type State: Serialize + DeserializeOwned;
+
+    // Written along the state, bump it when State changes and register a migration from the
+    // previous version with ComponentRegistry::register_migration
+    const VERSION: u32 = 1;
+
+    fn save_state(&self) -> Self::State;
+
+    fn restore_state(input: &Self::Input, state: Self::State) -> Self;
+}
+
+// "/Name:key" for keyed nodes, "/Name" otherwise
+pub(crate) fn path_segment(name: &str, key: &Option<String>) -> String {
+    match key {
+        Some(key) => format!("/{}:{}", name, key),
+        None => format!("/{}", name),
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    error::Error,
+    rc::Rc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    components::Group,
+    embeddable::context_holder::ContextContainer,
+    error::MachineTreeError,
+    key::{AbsComponent, AnyBox, NamedInput, Seed},
+    node::{component_utils::hold, Component},
+};
+
+use super::PersistentComponent;
+
+type SeedNode = fn(Value, String) -> Result<Seed, serde_json::Error>;
+type SaveNode = fn(&dyn Any, &AnyBox) -> Result<(Value, Value), MachineTreeError>;
+type RestoreNode = fn(Value, Value) -> Result<(AnyBox, AbsComponent), MachineTreeError>;
+type SaveContext = fn(&dyn Any) -> Result<Value, MachineTreeError>;
+type RestoreContext = fn(Value) -> Result<Rc<dyn Any>, MachineTreeError>;
+
+pub type MigrationError = Box<dyn Error + Send + Sync>;
+type Migrate = Box<dyn Fn(Value) -> Result<Value, MigrationError> + Send + Sync>;
+
+struct Migration {
+    from: u32,
+    to: u32,
+    migrate: Migrate,
+}
+
+pub(crate) struct ComponentEntry {
+    pub(crate) name: String,
+    pub(crate) type_id: TypeId,
+    pub(crate) version: u32,
+    // Input and state of a node, and back
+    pub(crate) save: SaveNode,
+    pub(crate) restore: RestoreNode,
+}
+
+pub(crate) struct ContextEntry {
+    pub(crate) name: String,
+    pub(crate) type_id: TypeId,
+    pub(crate) save: SaveContext,
+    pub(crate) restore: RestoreContext,
+}
+
+// Names components, to seed them from data, and the components and contexts that can be
+// written to a snapshot and read back.
+// Names are stored in snapshots and configurations, so they must stay the same across builds.
+// Group is registered from the start, the other built-in components cannot be persisted.
+pub struct ComponentRegistry {
+    seeds: HashMap<String, SeedNode>,
+    components: HashMap<String, ComponentEntry>,
+    component_names: HashMap<TypeId, String>,
+    contexts: HashMap<String, ContextEntry>,
+    context_names: HashMap<TypeId, String>,
+    migrations: HashMap<TypeId, Vec<Migration>>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        ComponentRegistry {
+            seeds: HashMap::new(),
+            components: HashMap::new(),
+            component_names: HashMap::new(),
+            contexts: HashMap::new(),
+            context_names: HashMap::new(),
+            migrations: HashMap::new(),
+        }
+        .register_persistent::<Group>("Group")
+    }
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registering another component under a taken name replaces it
+    pub fn register<Machine>(mut self, name: impl Into<String>) -> Self
+    where
+        Machine: Component,
+        Machine::Input: DeserializeOwned,
+    {
+        self.seeds.insert(name.into(), seed_node::<Machine>);
+        self
+    }
+
+    // Also registers the component for ComponentRegistry::seed
+    pub fn register_persistent<Machine>(self, name: impl Into<String>) -> Self
+    where
+        Machine: PersistentComponent,
+        Machine::Input: Serialize + DeserializeOwned,
+    {
+        let name = name.into();
+        let mut registry = self.register::<Machine>(name.clone());
+        registry.insert_persistent::<Machine>(name);
+        registry
+    }
+
+    fn insert_persistent<Machine>(&mut self, name: String)
+    where
+        Machine: PersistentComponent,
+        Machine::Input: Serialize + DeserializeOwned,
+    {
+        let type_id = TypeId::of::<Machine>();
+        self.component_names.insert(type_id, name.clone());
+        self.components.insert(
+            name.clone(),
+            ComponentEntry {
+                name,
+                type_id,
+                version: Machine::VERSION,
+                save: save_node::<Machine>,
+                restore: restore_node::<Machine>,
+            },
+        );
+    }
+
+    pub fn register_context<Container>(mut self, name: impl Into<String>) -> Self
+    where
+        Container: ContextContainer,
+        Container::Inner: Serialize + DeserializeOwned,
+    {
+        let name = name.into();
+        let type_id = TypeId::of::<Container>();
+        self.context_names.insert(type_id, name.clone());
+        self.contexts.insert(
+            name.clone(),
+            ContextEntry {
+                name,
+                type_id,
+                save: save_context::<Container>,
+                restore: restore_context::<Container>,
+            },
+        );
+        self
+    }
+
+    // Seed of the component registered as `name`, with its input deserialized from `input`
+    pub fn seed(&self, name: &str, input: Value, key: String) -> Result<Seed, MachineTreeError> {
+        let seed = self
+            .seeds
+            .get(name)
+            .ok_or_else(|| MachineTreeError::UnknownComponent {
+                name: name.to_string(),
+            })?;
+        let mut seed =
+            seed(input.clone(), key).map_err(|error| MachineTreeError::InvalidInput {
+                component: name.to_string(),
+                reason: error.to_string(),
+            })?;
+        seed.data.named = Some(NamedInput {
+            component: name.to_string(),
+            input,
+        });
+        Ok(seed)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.seeds.contains_key(name)
+    }
+
+    // Convert a saved state of `Machine` from version `from` to version `to`.
+    // Restoring chains the fewest migrations that lead to the current version.
+    pub fn register_migration<Machine>(
+        mut self,
+        from: u32,
+        to: u32,
+        migrate: impl Fn(Value) -> Result<Value, MigrationError> + Send + Sync + 'static,
+    ) -> Self
+    where
+        Machine: PersistentComponent,
+    {
+        self.migrations
+            .entry(TypeId::of::<Machine>())
+            .or_default()
+            .push(Migration {
+                from,
+                to,
+                migrate: Box::new(migrate),
+            });
+        self
+    }
+
+    // Bring a state saved at version `from` to the entry's version, `path` names the node
+    pub(crate) fn migrate(
+        &self,
+        entry: &ComponentEntry,
+        from: u32,
+        state: Value,
+        path: &str,
+    ) -> Result<Value, MachineTreeError> {
+        let migrations = self
+            .migrations
+            .get(&entry.type_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        // Breadth first, so that the shortest chain wins
+        let mut chains: HashMap<u32, Vec<&Migration>> = HashMap::from([(from, vec![])]);
+        let mut pending = VecDeque::from([from]);
+        while let Some(version) = pending.pop_front() {
+            if version == entry.version {
+                break;
+            }
+            let chain = chains.get(&version).cloned().unwrap_or_default();
+            migrations
+                .iter()
+                .filter(|migration| migration.from == version)
+                .for_each(|migration| {
+                    if let Entry::Vacant(vacant) = chains.entry(migration.to) {
+                        let mut next = chain.clone();
+                        next.push(migration);
+                        vacant.insert(next);
+                        pending.push_back(migration.to);
+                    }
+                });
+        }
+
+        let chain =
+            chains
+                .remove(&entry.version)
+                .ok_or_else(|| MachineTreeError::NoMigrationPath {
+                    path: path.to_string(),
+                    from,
+                    to: entry.version,
+                })?;
+        chain.into_iter().try_fold(state, |state, migration| {
+            (migration.migrate)(state).map_err(|error| MachineTreeError::MigrationFailed {
+                path: path.to_string(),
+                from: migration.from,
+                to: migration.to,
+                reason: error.to_string(),
+            })
+        })
+    }
+
+    pub(crate) fn component_by_name(&self, name: &str) -> Option<&ComponentEntry> {
+        self.components.get(name)
+    }
+
+    pub(crate) fn component_by_type(&self, type_id: TypeId) -> Option<&ComponentEntry> {
+        self.components.get(self.component_names.get(&type_id)?)
+    }
+
+    pub(crate) fn context_by_name(&self, name: &str) -> Option<&ContextEntry> {
+        self.contexts.get(name)
+    }
+
+    pub(crate) fn context_by_type(&self, type_id: TypeId) -> Option<&ContextEntry> {
+        self.contexts.get(self.context_names.get(&type_id)?)
+    }
+}
+
+fn seed_node<Machine>(input: Value, key: String) -> Result<Seed, serde_json::Error>
+where
+    Machine: Component,
+    Machine::Input: DeserializeOwned,
+{
+    Ok(Machine::seed(serde_json::from_value(input)?, key))
+}
+
+fn save_node<Machine>(
+    component: &dyn Any,
+    input: &AnyBox,
+) -> Result<(Value, Value), MachineTreeError>
+where
+    Machine: PersistentComponent,
+    Machine::Input: Serialize,
+{
+    let component = component
+        .downcast_ref::<Machine>()
+        .ok_or(MachineTreeError::TypeMismatch)?;
+    let input = input
+        .downcast_ref::<Machine::Input>()
+        .ok_or(MachineTreeError::TypeMismatch)?;
+    Ok((
+        serde_json::to_value(input)?,
+        serde_json::to_value(component.save_state())?,
+    ))
+}
+
+fn restore_node<Machine>(
+    input: Value,
+    state: Value,
+) -> Result<(AnyBox, AbsComponent), MachineTreeError>
+where
+    Machine: PersistentComponent,
+    Machine::Input: DeserializeOwned,
+{
+    let input: Machine::Input = serde_json::from_value(input)?;
+    let machine = Machine::restore_state(&input, serde_json::from_value(state)?);
+    Ok((Box::new(input), hold(machine)))
+}
+
+fn save_context<Container>(value: &dyn Any) -> Result<Value, MachineTreeError>
+where
+    Container: ContextContainer,
+    Container::Inner: Serialize,
+{
+    let value = value
+        .downcast_ref::<Container::Inner>()
+        .ok_or(MachineTreeError::TypeMismatch)?;
+    Ok(serde_json::to_value(value)?)
+}
+
+fn restore_context<Container>(value: Value) -> Result<Rc<dyn Any>, MachineTreeError>
+where
+    Container: ContextContainer,
+    Container::Inner: DeserializeOwned,
+{
+    let value: Container::Inner = serde_json::from_value(value)?;
+    Ok(Rc::new(value))
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{error::MachineTreeError, spec::TreeSpec};
+
+// Nodes are listed in pre-order, so parents precede their children and siblings keep their order
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TreeSnapshot {
+    pub(crate) nodes: Vec<NodeSnapshot>,
+    pub(crate) root_contexts: Vec<ContextSnapshot>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NodeSnapshot {
+    pub(crate) id: u64,
+    pub(crate) parent: Option<u64>,
+    pub(crate) children: Vec<u64>,
+    pub(crate) component: String,
+    pub(crate) key: Option<String>,
+    pub(crate) input: Value,
+    // PersistentComponent::VERSION of the state
+    #[serde(default = "first_version")]
+    pub(crate) version: u32,
+    pub(crate) state: Value,
+    pub(crate) priority: i32,
+    pub(crate) contexts: Vec<ContextSnapshot>,
+    // Seed::with_children of the node, as seeded from the registry
+    #[serde(default)]
+    pub(crate) declared_children: Vec<TreeSpec>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ContextSnapshot {
+    pub(crate) name: String,
+    pub(crate) value: Value,
+    // Ids of the nodes that read the context
+    pub(crate) subscribers: Vec<u64>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+// Nodes changed by a committed render pass, as appended to the write-ahead log
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PassRecord {
+    pub(crate) sequence: u64,
+    // Mounted and updated nodes
+    pub(crate) updated: Vec<NodeSnapshot>,
+    pub(crate) unmounted: Vec<u64>,
+    pub(crate) root_contexts: Option<Vec<ContextSnapshot>>,
+}
+
+impl TreeSnapshot {
+    pub(crate) fn apply(&mut self, record: PassRecord) -> Result<(), MachineTreeError> {
+        let PassRecord {
+            updated,
+            unmounted,
+            root_contexts,
+            ..
+        } = record;
+        let mut nodes: HashMap<u64, NodeSnapshot> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .chain(updated)
+            .map(|node| (node.id, node))
+            .collect();
+        unmounted.iter().for_each(|id| {
+            nodes.remove(id);
+        });
+        if let Some(root_contexts) = root_contexts {
+            self.root_contexts = root_contexts;
+        }
+
+        // Walk the children lists from the root to restore the pre-order
+        let root = nodes
+            .values()
+            .find(|node| node.parent.is_none())
+            .map(|node| node.id)
+            .ok_or_else(|| MachineTreeError::InvalidSnapshot("no root".to_string()))?;
+        let mut pending = vec![root];
+        while let Some(id) = pending.pop() {
+            if let Some(node) = nodes.remove(&id) {
+                pending.extend(node.children.iter().rev());
+                self.nodes.push(node);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::MachineTreeError;
+
+use super::snapshot::{ContextSnapshot, NodeSnapshot, PassRecord, TreeSnapshot};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const CHECKPOINT_TEMP_FILE: &str = "checkpoint.json.tmp";
+const LOG_FILE: &str = "wal.jsonl";
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    // Sequence of the last pass included in the tree
+    sequence: u64,
+    tree: TreeSnapshot,
+}
+
+// Keeps a tree in a local directory: every committed render pass is appended to a write-ahead
+// log, which is compacted into a checkpoint every `checkpoint_interval` passes.
+// Attach it through NodeHostBuilder::store, and read it back with NodeHost::recover.
+pub struct FileStore {
+    dir: PathBuf,
+    checkpoint_interval: u64,
+    sequence: u64,
+    passes_since_checkpoint: u64,
+    log: Option<File>,
+    // What the store already holds, so that a pass only writes the nodes it changed
+    written_nodes: HashMap<u64, NodeSnapshot>,
+    written_root_contexts: Vec<ContextSnapshot>,
+}
+
+impl FileStore {
+    pub fn open(dir: impl AsRef<Path>) -> Result<FileStore, MachineTreeError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(FileStore {
+            dir: dir.as_ref().to_path_buf(),
+            checkpoint_interval: 100,
+            sequence: 0,
+            passes_since_checkpoint: 0,
+            log: None,
+            written_nodes: Default::default(),
+            written_root_contexts: Default::default(),
+        })
+    }
+
+    pub fn checkpoint_interval(mut self, passes: u64) -> Self {
+        self.checkpoint_interval = passes.max(1);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // A new store starts with a checkpoint of the whole tree
+    pub(crate) fn needs_checkpoint(&self) -> bool {
+        self.log.is_none() || self.passes_since_checkpoint >= self.checkpoint_interval
+    }
+
+    // The last checkpoint with the passes logged after it, None if nothing was stored yet
+    pub(crate) fn read(&mut self) -> Result<Option<TreeSnapshot>, MachineTreeError> {
+        let checkpoint = match File::open(self.dir.join(CHECKPOINT_FILE)) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let Checkpoint { sequence, mut tree } =
+            serde_json::from_reader(BufReader::new(checkpoint))?;
+        self.sequence = sequence;
+
+        let log = match File::open(self.dir.join(LOG_FILE)) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Some(tree)),
+            Err(error) => return Err(error.into()),
+        };
+        let lines = BufReader::new(log).lines().collect::<Result<Vec<_>, _>>()?;
+        let last_line = lines.len();
+        for (line_number, line) in (1..).zip(lines) {
+            let record: PassRecord = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                // The process died while appending the last pass, which was never committed
+                Err(_) if line_number == last_line => break,
+                Err(error) => {
+                    return Err(MachineTreeError::InvalidSnapshot(format!(
+                        "{} line {}: {}",
+                        LOG_FILE, line_number, error
+                    )))
+                }
+            };
+            // Passes already compacted into the checkpoint
+            if record.sequence <= self.sequence {
+                continue;
+            }
+            self.sequence = record.sequence;
+            tree.apply(record)?;
+        }
+
+        Ok(Some(tree))
+    }
+
+    // Replace the checkpoint with `tree` and start an empty log
+    pub(crate) fn write_checkpoint(&mut self, tree: TreeSnapshot) -> Result<(), MachineTreeError> {
+        let checkpoint = Checkpoint {
+            sequence: self.sequence,
+            tree,
+        };
+        let temp_path = self.dir.join(CHECKPOINT_TEMP_FILE);
+        let mut temp = File::create(&temp_path)?;
+        serde_json::to_writer(&mut temp, &checkpoint)?;
+        temp.sync_all()?;
+        // The rename is atomic, a crash leaves either checkpoint in place with its log
+        fs::rename(&temp_path, self.dir.join(CHECKPOINT_FILE))?;
+        self.sync_dir()?;
+
+        // Synced again so that the entry of a new log survives along with its passes
+        let log = File::create(self.dir.join(LOG_FILE))?;
+        self.sync_dir()?;
+        self.log = Some(log);
+        self.passes_since_checkpoint = 0;
+        let Checkpoint { tree, .. } = checkpoint;
+        self.written_root_contexts = tree.root_contexts;
+        self.written_nodes = tree.nodes.into_iter().map(|node| (node.id, node)).collect();
+
+        Ok(())
+    }
+
+    fn sync_dir(&self) -> Result<(), MachineTreeError> {
+        Ok(File::open(&self.dir)?.sync_all()?)
+    }
+
+    // Append the nodes of a pass that differ from the store, once they are on disk
+    pub(crate) fn write_pass(
+        &mut self,
+        nodes: Vec<NodeSnapshot>,
+        unmounted: Vec<u64>,
+        root_contexts: Vec<ContextSnapshot>,
+    ) -> Result<(), MachineTreeError> {
+        let updated = nodes
+            .into_iter()
+            .filter(|node| self.written_nodes.get(&node.id) != Some(node))
+            .collect::<Vec<_>>();
+        let unmounted = unmounted
+            .into_iter()
+            .filter(|id| self.written_nodes.contains_key(id))
+            .collect::<Vec<_>>();
+        let root_contexts = Some(root_contexts)
+            .filter(|root_contexts| root_contexts != &self.written_root_contexts);
+        if updated.is_empty() && unmounted.is_empty() && root_contexts.is_none() {
+            return Ok(());
+        }
+
+        let log = match &mut self.log {
+            Some(log) => log,
+            None => {
+                return Err(MachineTreeError::Storage(
+                    "no checkpoint written yet".to_string(),
+                ))
+            }
+        };
+        let record = PassRecord {
+            sequence: self.sequence + 1,
+            updated,
+            unmounted,
+            root_contexts,
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let written = log.write_all(&line).and_then(|_| log.sync_data());
+        if let Err(error) = written {
+            // The log may end with a torn line now, the next pass starts over from a checkpoint
+            self.log = None;
+            return Err(error.into());
+        }
+
+        self.sequence = record.sequence;
+        self.passes_since_checkpoint += 1;
+        record.unmounted.iter().for_each(|id| {
+            self.written_nodes.remove(id);
+        });
+        record.updated.into_iter().for_each(|node| {
+            self.written_nodes.insert(node.id, node);
+        });
+        if let Some(root_contexts) = record.root_contexts {
+            self.written_root_contexts = root_contexts;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{error::MachineTreeError, key::Seed, persistence::ComponentRegistry};
+
+// A tree of registered components described as data, see NodeHostBuilder::spec and
+// NodeHost::reload. Children are declared with Seed::with_children, after the ones the
+// component steps itself.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TreeSpec {
+    pub component: String,
+    // Unique among siblings, matches the live node on reload
+    pub key: String,
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default)]
+    pub children: Vec<TreeSpec>,
+}
+
+impl TreeSpec {
+    // Documents that do not describe a TreeSpec fail with MachineTreeError::InvalidSpec, at the
+    // field that could not be read
+    pub fn from_json(document: &str) -> Result<TreeSpec, MachineTreeError> {
+        let mut deserializer = serde_json::Deserializer::from_str(document);
+        let spec = serde_path_to_error::deserialize(&mut deserializer).map_err(invalid_spec)?;
+        deserializer.end()?;
+        Ok(spec)
+    }
+
+    // Inputs may be written as RON structs, like `(queue: "emails")`, read through ron::Value.
+    // Syntax errors carry their position in the document instead of a path.
+    pub fn from_ron(document: &str) -> Result<TreeSpec, MachineTreeError> {
+        let value: ron::Value = ron::from_str(document)?;
+        serde_path_to_error::deserialize(value).map_err(invalid_spec)
+    }
+
+    // Validate the whole tree against `registry` before building any seed. Errors point at
+    // the field of the document, as in "$.children[1].input".
+    pub fn seed(&self, registry: &ComponentRegistry) -> Result<Seed, MachineTreeError> {
+        self.seed_at(registry, "$")
+    }
+
+    // Spec of a seed built by ComponentRegistry::seed, None for seeds built in Rust
+    pub(crate) fn from_seed(seed: &Seed) -> Option<TreeSpec> {
+        let named = seed.data.named.as_ref()?;
+        Some(TreeSpec {
+            component: named.component.clone(),
+            key: seed.key.key.clone()?,
+            input: named.input.clone(),
+            children: seed
+                .data
+                .declared_children
+                .iter()
+                .map(TreeSpec::from_seed)
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    fn seed_at(&self, registry: &ComponentRegistry, path: &str) -> Result<Seed, MachineTreeError> {
+        let seed = registry
+            .seed(&self.component, self.input.clone(), self.key.clone())
+            .map_err(|error| {
+                let field = match error {
+                    MachineTreeError::UnknownComponent { .. } => "component",
+                    _ => "input",
+                };
+                MachineTreeError::InvalidSpec {
+                    path: format!("{}.{}", path, field),
+                    reason: error.to_string(),
+                }
+            })?;
+
+        let mut keys = HashSet::new();
+        let children = self
+            .children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                let child_path = format!("{}.children[{}]", path, index);
+                if !keys.insert(&child.key) {
+                    return Err(MachineTreeError::InvalidSpec {
+                        path: format!("{}.key", child_path),
+                        reason: format!("duplicate key {:?}", child.key),
+                    });
+                }
+                child.seed_at(registry, &child_path)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(seed.with_children(children))
+    }
+}
+
+fn invalid_spec<Error>(error: serde_path_to_error::Error<Error>) -> MachineTreeError
+where
+    Error: std::error::Error,
+{
+    // The path of the document itself is "."
+    let path = match error.path().to_string().as_str() {
+        "." => "$".to_string(),
+        path => format!("$.{}", path),
+    };
+    MachineTreeError::InvalidSpec {
+        path,
+        reason: error.into_inner().to_string(),
+    }
+}
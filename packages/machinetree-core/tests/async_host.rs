@@ -0,0 +1,228 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use machinetree_core::{
+    async_component::{AsyncComponent, AsyncScope},
+    key::Seed,
+    node::{input_changed, Component, NodeHandle},
+    node_host::{async_host::AsyncNodeHost, NodeControl, NodeHost},
+};
+
+// Future resolving once opened from any thread
+#[derive(Clone, Default)]
+struct Gate(Arc<Mutex<(bool, Option<Waker>)>>);
+
+impl Gate {
+    fn open(&self) {
+        let mut gate = self.0.lock().unwrap();
+        gate.0 = true;
+        if let Some(waker) = gate.1.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for Gate {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut gate = self.0.lock().unwrap();
+        match gate.0 {
+            true => Poll::Ready(()),
+            false => {
+                gate.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+type Steps = Arc<Mutex<Vec<String>>>;
+
+// Records its steps
+struct Leaf;
+
+impl Component for Leaf {
+    type Input = (String, Steps);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Leaf
+    }
+
+    fn step(&mut self, _: &mut NodeControl, (name, steps): &Self::Input) -> Vec<Seed> {
+        steps.lock().unwrap().push(name.clone());
+        vec![]
+    }
+}
+
+// Shows a loading leaf until the gate opens
+struct Loader;
+
+impl AsyncComponent for Loader {
+    type Input = (Gate, Steps);
+
+    fn construct(_: &Self::Input) -> Self {
+        Loader
+    }
+
+    fn run(
+        &mut self,
+        scope: AsyncScope,
+        (gate, steps): &Self::Input,
+    ) -> impl Future<Output = Vec<Seed>> + 'static {
+        let (gate, steps) = (gate.clone(), steps.clone());
+        async move {
+            scope.emit(vec![Leaf::seed(
+                ("loading".to_string(), steps.clone()),
+                "leaf".to_string(),
+            )]);
+            gate.await;
+            vec![Leaf::seed(
+                ("loaded".to_string(), steps),
+                "leaf".to_string(),
+            )]
+        }
+    }
+}
+
+#[test]
+fn async_hosts_wait_for_woken_futures() {
+    let gate = Gate::default();
+    let steps = Steps::default();
+    let host = NodeHost::make_root(Loader::seed(
+        (gate.clone(), steps.clone()),
+        "loader".to_string(),
+    ));
+    let shutdown_handle = host.shutdown_handle();
+    let mut host = AsyncNodeHost::new(host);
+
+    while !steps.lock().unwrap().contains(&"loading".to_string()) {
+        pollster::block_on(host.next_report()).unwrap();
+    }
+
+    let opener = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        gate.open();
+    });
+    while !steps.lock().unwrap().contains(&"loaded".to_string()) {
+        pollster::block_on(host.next_report()).unwrap();
+    }
+    opener.join().unwrap();
+    let mut stepped = steps.lock().unwrap().clone();
+    stepped.dedup();
+    assert_eq!(stepped, vec!["loading", "loaded"]);
+
+    shutdown_handle.shutdown().unwrap();
+    assert!(pollster::block_on(host.next_report()).is_none());
+}
+
+#[derive(Default)]
+struct Runs {
+    started: Cell<u32>,
+    generation: Cell<u32>,
+    parent: RefCell<Option<NodeHandle>>,
+}
+
+// Counts the futures it starts, which never resolve, restarting on a new generation
+struct Restarting;
+
+impl AsyncComponent for Restarting {
+    type Input = (u32, Rc<Runs>);
+
+    fn construct(_: &Self::Input) -> Self {
+        Restarting
+    }
+
+    fn run(
+        &mut self,
+        _: AsyncScope,
+        (_, runs): &Self::Input,
+    ) -> impl Future<Output = Vec<Seed>> + 'static {
+        runs.started.set(runs.started.get() + 1);
+        std::future::pending()
+    }
+
+    fn should_restart(&self, old_input: &Self::Input, new_input: &Self::Input) -> bool {
+        input_changed(&old_input.0, &new_input.0)
+    }
+}
+
+struct Parent;
+
+impl Component for Parent {
+    type Input = Rc<Runs>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Parent
+    }
+
+    fn step(&mut self, control: &mut NodeControl, runs: &Self::Input) -> Vec<Seed> {
+        runs.parent.replace(Some(control.handle()));
+        vec![Restarting::seed(
+            (runs.generation.get(), runs.clone()),
+            "restarting".to_string(),
+        )]
+    }
+}
+
+#[test]
+fn async_nodes_restart_only_on_accepted_inputs() {
+    let runs = Rc::new(Runs::default());
+    let mut host = NodeHost::make_root(Parent::seed(runs.clone(), "parent".to_string()));
+    let rerender_parent = |host: &mut NodeHost| {
+        runs.parent.borrow().as_ref().unwrap().rerender().unwrap();
+        host.run_until_idle();
+    };
+    host.run_until_idle();
+    assert_eq!(runs.started.get(), 1);
+
+    rerender_parent(&mut host);
+    assert_eq!(runs.started.get(), 1);
+
+    runs.generation.set(1);
+    rerender_parent(&mut host);
+    assert_eq!(runs.started.get(), 2);
+}
+
+struct Ticking;
+
+impl Component for Ticking {
+    type Input = Steps;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Ticking
+    }
+
+    fn on_mount(&mut self, control: &mut NodeControl) {
+        control.set_interval(Duration::from_millis(5));
+    }
+
+    fn step(&mut self, _: &mut NodeControl, steps: &Self::Input) -> Vec<Seed> {
+        steps.lock().unwrap().push("tick".to_string());
+        vec![]
+    }
+}
+
+#[test]
+fn async_hosts_wake_for_timers() {
+    let steps = Steps::default();
+    let mut host = AsyncNodeHost::new(NodeHost::make_root(Ticking::seed(
+        steps.clone(),
+        "ticking".to_string(),
+    )));
+
+    while steps.lock().unwrap().len() < 5 {
+        pollster::block_on(host.next_report()).unwrap();
+    }
+}
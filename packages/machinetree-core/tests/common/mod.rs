@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use machinetree_core::{
+    key::Seed,
+    node::{input_changed, Component},
+    node_host::NodeControl,
+    persistence::{ComponentRegistry, PersistentComponent},
+};
+
+// Counts its steps
+pub struct Counter(u32);
+
+impl Component for Counter {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Counter(0)
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        self.0 += 1;
+        vec![]
+    }
+
+    fn should_step(&self, old_input: &Self::Input, new_input: &Self::Input) -> bool {
+        input_changed(old_input, new_input)
+    }
+}
+
+impl PersistentComponent for Counter {
+    type State = u32;
+
+    fn save_state(&self) -> Self::State {
+        self.0
+    }
+
+    fn restore_state(_: &Self::Input, state: Self::State) -> Self {
+        Counter(state)
+    }
+}
+
+pub fn registry() -> Arc<ComponentRegistry> {
+    Arc::new(ComponentRegistry::new().register_persistent::<Counter>("Counter"))
+}
@@ -0,0 +1,148 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use machinetree_core::{
+    embeddable::context_holder::ContextContainer,
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{NodeControl, NodeHost},
+};
+
+struct Theme;
+
+impl Component for Theme {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Theme
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+impl ContextContainer for Theme {
+    type Inner = String;
+}
+
+// Shared with the test, to step the provider again and see what the readers read
+#[derive(Default)]
+struct Probe {
+    shadowing: Cell<bool>,
+    handle: RefCell<Option<NodeHandle>>,
+    reads: RefCell<Vec<(String, String)>>,
+}
+
+struct Reader;
+
+impl Component for Reader {
+    type Input = (String, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Reader
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (name, probe): &Self::Input) -> Vec<Seed> {
+        let theme = control.use_context().get_context::<Theme>().unwrap();
+        probe
+            .reads
+            .borrow_mut()
+            .push((name.clone(), (*theme).clone()));
+        vec![]
+    }
+
+    // Only a context can step it again
+    fn should_step(&self, _: &Self::Input, _: &Self::Input) -> bool {
+        false
+    }
+}
+
+struct Provider;
+
+impl Component for Provider {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Provider
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.handle.replace(Some(control.handle()));
+        if probe.shadowing.get() {
+            control
+                .use_context()
+                .set_context::<Theme>("provider".to_string());
+        }
+        let reader = ("inner".to_string(), probe.clone());
+        vec![Reader::seed(reader, "inner".to_string())]
+    }
+}
+
+struct Root;
+
+impl Component for Root {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Root
+    }
+
+    fn step(&mut self, _: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        let reader = ("outer".to_string(), probe.clone());
+        vec![
+            Provider::seed(probe.clone(), "provider".to_string()),
+            Reader::seed(reader, "outer".to_string()),
+        ]
+    }
+}
+
+#[test]
+fn shadowing_a_context_steps_the_readers_under_the_new_provider() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::builder()
+        .provide::<Theme>("root".to_string())
+        .root(Root::seed(probe.clone(), "root".to_string()));
+    host.run_until_idle();
+
+    let read = |name: &str, theme: &str| (name.to_string(), theme.to_string());
+    let mut first_reads = probe.reads.take();
+    first_reads.sort();
+    assert_eq!(
+        first_reads,
+        vec![read("inner", "root"), read("outer", "root")]
+    );
+
+    probe.shadowing.set(true);
+    probe.handle.borrow().as_ref().unwrap().rerender().unwrap();
+    host.run_until_idle();
+
+    assert_eq!(probe.reads.take(), vec![read("inner", "provider")]);
+}
+
+#[test]
+fn replacing_a_root_context_steps_its_readers() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::builder()
+        .provide::<Theme>("root".to_string())
+        .root(Root::seed(probe.clone(), "root".to_string()));
+    host.run_until_idle();
+    probe.reads.take();
+
+    let previous = host.set_root_context::<Theme>("replaced".to_string());
+    assert_eq!(previous.as_deref().map(String::as_str), Some("root"));
+    let report = host.run_until_idle();
+
+    let mut reads = probe.reads.take();
+    reads.sort();
+    let read = |name: &str| (name.to_string(), "replaced".to_string());
+    assert_eq!(reads, vec![read("inner"), read("outer")]);
+    // Only the readers stepped
+    assert_eq!(report.rendered_keys.len(), 2);
+}
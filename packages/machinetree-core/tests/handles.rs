@@ -0,0 +1,96 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, NodeHandle, NodeHandleError},
+    node_host::{NodeControl, NodeHost},
+};
+
+// Shared with the test, to hide the child and reach the nodes
+#[derive(Default)]
+struct Probe {
+    hidden: Cell<bool>,
+    handles: RefCell<HashMap<&'static str, NodeHandle>>,
+}
+
+impl Probe {
+    fn handle(&self, name: &str) -> NodeHandle {
+        self.handles.borrow()[name].clone()
+    }
+}
+
+struct Child;
+
+impl Component for Child {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Child
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.handles.borrow_mut().insert("child", control.handle());
+        vec![]
+    }
+}
+
+struct Parent;
+
+impl Component for Parent {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Parent
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe
+            .handles
+            .borrow_mut()
+            .insert("parent", control.handle());
+        match probe.hidden.get() {
+            true => vec![],
+            false => vec![Child::seed(probe.clone(), "child".to_string())],
+        }
+    }
+}
+
+#[test]
+fn handles_of_unmounted_nodes_are_dead() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Parent::seed(probe.clone(), "parent".to_string()));
+    host.run_until_idle();
+
+    let child = probe.handle("child");
+    assert!(child.is_alive());
+    assert_eq!(child.rerender(), Ok(()));
+    assert_eq!(host.run_until_idle().rendered_keys.len(), 1);
+
+    probe.hidden.set(true);
+    probe.handle("parent").rerender().unwrap();
+    host.run_until_idle();
+
+    assert!(!child.is_alive());
+    assert_eq!(child.rerender(), Err(NodeHandleError::Unmounted));
+    assert!(host.run_until_idle().rendered_keys.is_empty());
+}
+
+#[test]
+fn handles_are_dead_once_the_host_is_dropped() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Parent::seed(probe.clone(), "parent".to_string()));
+    host.run_until_idle();
+    drop(host);
+
+    for name in ["parent", "child"] {
+        let handle = probe.handle(name);
+        assert!(!handle.is_alive());
+        assert_eq!(handle.rerender(), Err(NodeHandleError::Unmounted));
+    }
+}
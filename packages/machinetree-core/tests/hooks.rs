@@ -0,0 +1,202 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use machinetree_core::{
+    embeddable::state_manager::StateHandle,
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{NodeControl, NodeHost},
+};
+
+// Shared with the test, to step the root again and see what the nodes did
+#[derive(Default)]
+struct Probe {
+    deps: Cell<u32>,
+    hidden: Cell<bool>,
+    handle: RefCell<Option<NodeHandle>>,
+    child_handle: RefCell<Option<NodeHandle>>,
+    log: RefCell<Vec<String>>,
+    count: RefCell<Option<StateHandle<u32>>>,
+}
+
+impl Probe {
+    fn rerender(&self) {
+        self.handle.borrow().as_ref().unwrap().rerender().unwrap();
+    }
+
+    fn log(&self, entry: String) {
+        self.log.borrow_mut().push(entry);
+    }
+}
+
+struct Effected;
+
+impl Component for Effected {
+    type Input = (u32, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Effected
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (deps, probe): &Self::Input) -> Vec<Seed> {
+        let (deps, probe) = (*deps, probe.clone());
+        control.use_effect(deps, move || {
+            probe.log(format!("run{}", deps));
+            Some(Box::new(move || probe.log(format!("clean{}", deps))))
+        });
+        vec![]
+    }
+}
+
+struct EffectParent;
+
+impl Component for EffectParent {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        EffectParent
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.handle.replace(Some(control.handle()));
+        match probe.hidden.get() {
+            true => vec![],
+            false => vec![Effected::seed(
+                (probe.deps.get(), probe.clone()),
+                "effected".to_string(),
+            )],
+        }
+    }
+}
+
+#[test]
+fn effects_run_when_their_deps_change_and_clean_up_before_the_next_run() {
+    let probe = Rc::new(Probe {
+        deps: Cell::new(1),
+        ..Default::default()
+    });
+    let mut host = NodeHost::make_root(EffectParent::seed(probe.clone(), "root".to_string()));
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["run1"]);
+
+    // Same deps, the child steps without running its effect
+    probe.rerender();
+    assert_eq!(host.run_until_idle().rendered_keys.len(), 2);
+    assert!(probe.log.take().is_empty());
+
+    probe.deps.set(2);
+    probe.rerender();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["clean1", "run2"]);
+
+    // Unmounting runs the last cleanup
+    probe.hidden.set(true);
+    probe.rerender();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["clean2"]);
+}
+
+// Logs the value of its state on each step
+struct Stateful;
+
+impl Component for Stateful {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Stateful
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        let count = control.use_state(|| 0);
+        probe.log(format!("count{}", count.get()));
+        probe.count.replace(Some(count));
+        probe.handle.replace(Some(control.handle()));
+        vec![]
+    }
+}
+
+#[test]
+fn state_changes_step_the_node_again_and_the_value_is_kept() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Stateful::seed(probe.clone(), "stateful".to_string()));
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["count0"]);
+
+    let count = probe.count.borrow().clone().unwrap();
+    count.set(5).unwrap();
+    assert_eq!(host.run_until_idle().rendered_keys.len(), 1);
+    assert_eq!(probe.log.take(), ["count5"]);
+
+    count.update(|count| *count += 1).unwrap();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["count6"]);
+
+    // Steps for other reasons see the same value
+    probe.rerender();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["count6"]);
+}
+
+// Logs the input of its previous step
+struct Remembering;
+
+impl Component for Remembering {
+    type Input = (u32, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Remembering
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (_, probe): &Self::Input) -> Vec<Seed> {
+        let previous = control.previous_input::<Self::Input>();
+        probe.log(format!("{:?}", previous.map(|(deps, _)| deps)));
+        probe.child_handle.replace(Some(control.handle()));
+        vec![]
+    }
+}
+
+struct RememberingParent;
+
+impl Component for RememberingParent {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        RememberingParent
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.handle.replace(Some(control.handle()));
+        let input = (probe.deps.get(), probe.clone());
+        vec![Remembering::seed(input, "remembering".to_string())]
+    }
+}
+
+#[test]
+fn previous_input_holds_the_input_of_the_previous_step() {
+    let probe = Rc::new(Probe {
+        deps: Cell::new(1),
+        ..Default::default()
+    });
+    let root = RememberingParent::seed(probe.clone(), "root".to_string());
+    let mut host = NodeHost::make_root(root);
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["None"]);
+
+    probe.deps.set(2);
+    probe.rerender();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["Some(1)"]);
+
+    // Steps the node triggers itself received no new input
+    let child = probe.child_handle.borrow().clone().unwrap();
+    child.rerender().unwrap();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["None"]);
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{NodeControl, NodeHost},
+};
+
+// Shared with the test, to hide a subtree and see the hooks that ran
+#[derive(Default)]
+struct Probe {
+    hidden: Cell<bool>,
+    handle: RefCell<Option<NodeHandle>>,
+    log: RefCell<Vec<String>>,
+}
+
+// root
+// ├── a
+// │   └── a1
+// └── b
+fn children_of(name: &str, probe: &Probe) -> &'static [&'static str] {
+    match name {
+        "root" if probe.hidden.get() => &["b"],
+        "root" => &["a", "b"],
+        "a" => &["a1"],
+        _ => &[],
+    }
+}
+
+struct Node {
+    name: String,
+    probe: Rc<Probe>,
+}
+
+impl Node {
+    fn log(&self, hook: &str) {
+        let entry = format!("{} {}", hook, self.name);
+        self.probe.log.borrow_mut().push(entry);
+    }
+}
+
+impl Component for Node {
+    type Input = (String, Rc<Probe>);
+    type Message = ();
+
+    fn construct((name, probe): &Self::Input) -> Self {
+        Node {
+            name: name.clone(),
+            probe: probe.clone(),
+        }
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (name, probe): &Self::Input) -> Vec<Seed> {
+        if name == "root" {
+            probe.handle.replace(Some(control.handle()));
+        }
+        children_of(name, probe)
+            .iter()
+            .map(|child| Node::seed((child.to_string(), probe.clone()), child.to_string()))
+            .collect()
+    }
+
+    fn on_mount(&mut self, _: &mut NodeControl) {
+        self.log("mount");
+    }
+
+    fn on_unmount(&mut self, _: &mut NodeControl) {
+        self.log("unmount");
+    }
+}
+
+#[test]
+fn children_are_mounted_and_unmounted_before_their_parents() {
+    let probe = Rc::new(Probe::default());
+    let root = Node::seed(("root".to_string(), probe.clone()), "root".to_string());
+    let mut host = NodeHost::make_root(root);
+    host.run_until_idle();
+    assert_eq!(
+        probe.log.take(),
+        ["mount a1", "mount b", "mount a", "mount root"]
+    );
+
+    probe.hidden.set(true);
+    probe.handle.borrow().as_ref().unwrap().rerender().unwrap();
+    host.run_until_idle();
+    assert_eq!(probe.log.take(), ["unmount a1", "unmount a"]);
+
+    // Dropping the host unmounts what is left of the tree
+    drop(host);
+    assert_eq!(probe.log.take(), ["unmount b", "unmount root"]);
+}
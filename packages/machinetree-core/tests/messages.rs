@@ -0,0 +1,66 @@
+use std::{cell::RefCell, rc::Rc};
+
+use machinetree_core::{
+    error::MachineTreeError,
+    key::Seed,
+    node::{Component, Mailbox},
+    node_host::{NodeControl, NodeHost},
+};
+
+#[derive(Default)]
+struct Inbox {
+    received: RefCell<Vec<String>>,
+    mailbox: RefCell<Option<Mailbox<String>>>,
+}
+
+struct Receiving(Rc<Inbox>);
+
+impl Component for Receiving {
+    type Input = Rc<Inbox>;
+    type Message = String;
+
+    fn construct(inbox: &Self::Input) -> Self {
+        Receiving(inbox.clone())
+    }
+
+    fn step(&mut self, control: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        assert!(matches!(
+            control.mailbox::<Other>(),
+            Err(MachineTreeError::TypeMismatch)
+        ));
+        self.0.mailbox.replace(control.mailbox::<Receiving>().ok());
+        vec![]
+    }
+
+    fn on_message(&mut self, _: &mut NodeControl, message: Self::Message) {
+        self.0.received.borrow_mut().push(message);
+    }
+}
+
+// Same message type as Receiving
+struct Other;
+
+impl Component for Other {
+    type Input = ();
+    type Message = String;
+
+    fn construct(_: &Self::Input) -> Self {
+        Other
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+#[test]
+fn mailboxes_are_only_built_for_the_current_component() {
+    let inbox = Rc::new(Inbox::default());
+    let mut host = NodeHost::make_root(Receiving::seed(inbox.clone(), "receiving".to_string()));
+    host.run_until_idle();
+
+    let mailbox = inbox.mailbox.borrow().clone().unwrap();
+    mailbox.send("hello".to_string()).unwrap();
+    host.run_until_idle();
+    assert_eq!(*inbox.received.borrow(), vec!["hello".to_string()]);
+}
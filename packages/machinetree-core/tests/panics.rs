@@ -0,0 +1,292 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use machinetree_core::{
+    components::{ErrorBoundary, ErrorBoundaryInput},
+    error::MachineTreeError,
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{
+        failure::{FailureCause, StepError},
+        NodeControl, NodeHost,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Hook {
+    Construct,
+    ShouldStep,
+    Mount,
+    Effect,
+}
+
+// Shared with the test, to step the root again
+#[derive(Default)]
+struct Probe {
+    generation: Cell<u32>,
+    handle: RefCell<Option<NodeHandle>>,
+}
+
+impl Probe {
+    fn rerender(&self) {
+        self.handle.borrow().as_ref().unwrap().rerender().unwrap();
+    }
+}
+
+struct Fragile(Hook);
+
+impl Component for Fragile {
+    type Input = (Hook, u32);
+    type Message = ();
+
+    fn construct(input: &Self::Input) -> Self {
+        assert!(input.0 != Hook::Construct, "construct");
+        Fragile(input.0)
+    }
+
+    fn step(&mut self, control: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        let hook = input.0;
+        control.use_effect((), move || {
+            assert!(hook != Hook::Effect, "effect");
+            None
+        });
+        vec![]
+    }
+
+    fn should_step(&self, _: &Self::Input, new_input: &Self::Input) -> bool {
+        assert!(
+            new_input.1 == 0 || new_input.0 != Hook::ShouldStep,
+            "should_step"
+        );
+        true
+    }
+
+    fn on_mount(&mut self, _: &mut NodeControl) {
+        assert!(self.0 != Hook::Mount, "on_mount");
+    }
+}
+
+// Steps Fragile, panics of its constructor and should_step fail this step
+struct Parent;
+
+impl Component for Parent {
+    type Input = (Hook, u32);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Parent
+    }
+
+    fn step(&mut self, _: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        vec![Fragile::seed(*input, "fragile".to_string())]
+    }
+}
+
+struct Guarded;
+
+impl Component for Guarded {
+    type Input = (Hook, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Guarded
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (hook, probe): &Self::Input) -> Vec<Seed> {
+        probe.handle.replace(Some(control.handle()));
+        let child = Parent::seed((*hook, probe.generation.get()), "parent".to_string());
+        vec![ErrorBoundary::seed(
+            ErrorBoundaryInput::new(vec![child], |_| vec![]),
+            "boundary".to_string(),
+        )]
+    }
+}
+
+fn caught_panic(hook: Hook) -> String {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Guarded::seed((hook, probe.clone()), "root".to_string()));
+    let mut report = host.run_until_idle();
+
+    if hook == Hook::ShouldStep {
+        probe.generation.set(1);
+        probe.rerender();
+        report = host.run_until_idle();
+    }
+
+    let failure = report
+        .failures
+        .first()
+        .expect("the panic is reported as a failure");
+    assert!(failure.boundary.is_some(), "the error boundary handles it");
+    match &failure.failure.cause {
+        FailureCause::Panic(message) => message.clone(),
+        FailureCause::Error(error) => panic!("unexpected error {}", error),
+    }
+}
+
+#[test]
+fn panics_outside_of_step_reach_the_error_boundary() {
+    assert_eq!(caught_panic(Hook::Construct), "construct");
+    assert_eq!(caught_panic(Hook::ShouldStep), "should_step");
+    assert_eq!(caught_panic(Hook::Mount), "on_mount");
+    assert_eq!(caught_panic(Hook::Effect), "effect");
+}
+
+struct Unmounting;
+
+impl Component for Unmounting {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Unmounting
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn on_unmount(&mut self, _: &mut NodeControl) {
+        panic!("{}", "on_unmount");
+    }
+}
+
+struct Toggle;
+
+impl Component for Toggle {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Toggle
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.handle.replace(Some(control.handle()));
+        // Shown in even generations
+        match probe.generation.get() % 2 == 0 {
+            true => vec![Unmounting::seed((), "child".to_string())],
+            false => vec![],
+        }
+    }
+}
+
+#[test]
+fn panics_while_unmounting_are_reported_and_the_node_is_removed() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Toggle::seed(probe.clone(), "root".to_string()));
+    assert!(host.run_until_idle().failures.is_empty());
+
+    probe.generation.set(1);
+    probe.rerender();
+    let report = host.run_until_idle();
+
+    assert_eq!(report.unlinked_node_pairs.len(), 1);
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.failures[0].boundary.is_none());
+
+    // The host remains usable
+    probe.generation.set(2);
+    probe.rerender();
+    assert_eq!(host.run_until_idle().rendered_keys.len(), 2);
+}
+
+struct HookedMount;
+
+impl Component for HookedMount {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        HookedMount
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn on_mount(&mut self, control: &mut NodeControl) {
+        control.use_state(|| 0);
+    }
+}
+
+#[test]
+fn hooks_outside_of_step_fail_the_node() {
+    let mut host = NodeHost::make_root(HookedMount::seed((), "hooked".to_string()));
+    let report = host.run_until_idle();
+
+    assert_eq!(report.failures.len(), 1);
+    assert!(matches!(
+        &report.failures[0].failure.cause,
+        FailureCause::Panic(message) if message.contains("use_state")
+    ));
+}
+
+struct Erring;
+
+impl Component for Erring {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Erring
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn try_step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Result<Vec<Seed>, StepError> {
+        Err("refused".into())
+    }
+}
+
+// Names the boundary that caught the failure
+fn boundary(
+    name: &'static str,
+    children: Vec<Seed>,
+    caught: &Rc<RefCell<Vec<&'static str>>>,
+) -> Seed {
+    let caught = caught.clone();
+    ErrorBoundary::seed(
+        ErrorBoundaryInput::new(children, move |_| {
+            caught.borrow_mut().push(name);
+            vec![]
+        }),
+        name.to_string(),
+    )
+}
+
+#[test]
+fn errors_returned_by_try_step_reach_the_closest_error_boundary() {
+    let caught = Rc::new(RefCell::new(vec![]));
+    let inner = boundary(
+        "inner",
+        vec![Erring::seed((), "erring".to_string())],
+        &caught,
+    );
+    let mut host = NodeHost::make_root(boundary("outer", vec![inner], &caught));
+    let report = host.run_until_idle();
+
+    assert_eq!(*caught.borrow(), ["inner"]);
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.failures[0].boundary.is_some());
+    assert!(matches!(
+        &report.failures[0].failure.cause,
+        FailureCause::Error(error) if error.to_string() == "refused"
+    ));
+}
+
+#[test]
+fn try_render_fails_with_errors_no_boundary_handled() {
+    let mut host = NodeHost::make_root(Erring::seed((), "erring".to_string()));
+
+    match host.try_render() {
+        Err(MachineTreeError::StepFailure(failure)) => {
+            assert_eq!(failure.cause.to_string(), "returned an error: refused")
+        }
+        _ => panic!("expected a StepFailure"),
+    }
+}
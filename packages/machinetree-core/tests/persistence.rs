@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use machinetree_core::{
+    components::{ErrorBoundary, ErrorBoundaryInput},
+    error::MachineTreeError,
+    key::Seed,
+    node::Component,
+    node_host::{NodeControl, NodeHost},
+    persistence::{ComponentRegistry, PersistentComponent},
+    spec::TreeSpec,
+};
+use serde_json::Value;
+
+mod common;
+
+use common::registry;
+
+fn states(snapshot: &[u8]) -> Vec<Value> {
+    let snapshot: Value = serde_json::from_slice(snapshot).unwrap();
+    snapshot["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| node["state"].clone())
+        .collect()
+}
+
+#[test]
+fn snapshots_restore_to_the_same_tree() {
+    let spec = TreeSpec::from_json(
+        r#"{
+            "component": "Group",
+            "key": "root",
+            "children": [
+                { "component": "Counter", "key": "a", "input": "first" },
+                { "component": "Counter", "key": "b", "input": "second" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut host = NodeHost::from_spec(&spec, registry()).unwrap();
+    host.run_until_idle();
+    let snapshot = host.snapshot().unwrap();
+    drop(host);
+
+    let mut restored = NodeHost::restore(&snapshot, registry()).unwrap();
+    assert_eq!(restored.snapshot().unwrap(), snapshot);
+
+    // Restored nodes keep their state and their declared children
+    let changed = TreeSpec::from_json(
+        r#"{
+            "component": "Group",
+            "key": "root",
+            "children": [
+                { "component": "Counter", "key": "a", "input": "changed" },
+                { "component": "Counter", "key": "b", "input": "second" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    restored.reload(&changed).unwrap();
+    restored.run_until_idle();
+    assert_eq!(
+        states(&restored.snapshot().unwrap()),
+        vec![Value::Null, Value::from(2), Value::from(1)]
+    );
+}
+
+struct Hooked;
+
+impl Component for Hooked {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Hooked
+    }
+
+    fn step(&mut self, control: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        control.use_state(|| 0);
+        vec![]
+    }
+}
+
+impl PersistentComponent for Hooked {
+    type State = ();
+
+    fn save_state(&self) -> Self::State {}
+
+    fn restore_state(_: &Self::Input, _: Self::State) -> Self {
+        Hooked
+    }
+}
+
+#[test]
+fn hook_state_cannot_be_persisted() {
+    let registry = Arc::new(ComponentRegistry::new().register_persistent::<Hooked>("Hooked"));
+    let mut host = NodeHost::builder()
+        .registry(registry)
+        .root(Hooked::seed("hooked".to_string(), "root".to_string()));
+    host.run_until_idle();
+
+    match host.snapshot() {
+        Err(MachineTreeError::NotPersistent { path, .. }) => assert_eq!(path, "/Hooked:root"),
+        _ => panic!("expected NotPersistent"),
+    }
+}
+
+#[test]
+fn built_in_boundaries_cannot_be_persisted() {
+    let mut host = NodeHost::builder()
+        .registry(registry())
+        .root(ErrorBoundary::seed(
+            ErrorBoundaryInput::new(vec![], |_| vec![]),
+            "boundary".to_string(),
+        ));
+    host.run_until_idle();
+
+    assert!(matches!(
+        host.snapshot(),
+        Err(MachineTreeError::UnregisteredComponent { .. })
+    ));
+}
+
+#[test]
+fn states_that_do_not_deserialize_name_their_node() {
+    let spec = TreeSpec::from_json(
+        r#"{
+            "component": "Group",
+            "key": "root",
+            "children": [{ "component": "Counter", "key": "a", "input": "first" }]
+        }"#,
+    )
+    .unwrap();
+    let mut host = NodeHost::from_spec(&spec, registry()).unwrap();
+    host.run_until_idle();
+    let mut snapshot: Value = serde_json::from_slice(&host.snapshot().unwrap()).unwrap();
+    snapshot["nodes"][1]["state"] = Value::from("not a count");
+    let snapshot = serde_json::to_vec(&snapshot).unwrap();
+
+    match NodeHost::restore(&snapshot, registry()) {
+        Err(MachineTreeError::RestoreFailed { path, .. }) => {
+            assert_eq!(path, "/Group:root/Counter:a")
+        }
+        _ => panic!("expected RestoreFailed"),
+    }
+}
+
+// State of the first version, a count
+struct Versioned1(u32);
+
+impl Component for Versioned1 {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Versioned1(7)
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+impl PersistentComponent for Versioned1 {
+    type State = u32;
+
+    fn save_state(&self) -> Self::State {
+        self.0
+    }
+
+    fn restore_state(_: &Self::Input, state: Self::State) -> Self {
+        Versioned1(state)
+    }
+}
+
+// The same component two versions later, the count gained a label
+struct Versioned3((u32, String));
+
+impl Component for Versioned3 {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Versioned3((0, String::new()))
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+impl PersistentComponent for Versioned3 {
+    type State = (u32, String);
+    const VERSION: u32 = 3;
+
+    fn save_state(&self) -> Self::State {
+        self.0.clone()
+    }
+
+    fn restore_state(_: &Self::Input, state: Self::State) -> Self {
+        Versioned3(state)
+    }
+}
+
+fn versioned_snapshot() -> Vec<u8> {
+    let registry =
+        Arc::new(ComponentRegistry::new().register_persistent::<Versioned1>("Versioned"));
+    let mut host = NodeHost::builder()
+        .registry(registry)
+        .root(Versioned1::seed("input".to_string(), "v".to_string()));
+    host.run_until_idle();
+    host.snapshot().unwrap()
+}
+
+fn upgraded(registry: ComponentRegistry) -> Result<NodeHost, MachineTreeError> {
+    let registry = registry.register_persistent::<Versioned3>("Versioned");
+    NodeHost::restore(&versioned_snapshot(), Arc::new(registry))
+}
+
+#[test]
+fn restoring_an_older_version_chains_its_migrations() {
+    let registry = ComponentRegistry::new()
+        .register_migration::<Versioned3>(1, 2, |count| Ok(Value::from(vec![count])))
+        .register_migration::<Versioned3>(2, 3, |mut state| {
+            state
+                .as_array_mut()
+                .ok_or("not a list")?
+                .push("label".into());
+            Ok(state)
+        });
+    let restored = upgraded(registry).unwrap();
+
+    let snapshot: Value = serde_json::from_slice(&restored.snapshot().unwrap()).unwrap();
+    assert_eq!(snapshot["nodes"][0]["version"], Value::from(3));
+    assert_eq!(
+        states(&restored.snapshot().unwrap()),
+        vec![serde_json::json!([7, "label"])]
+    );
+}
+
+#[test]
+fn missing_migrations_name_their_node() {
+    let registry = ComponentRegistry::new()
+        .register_migration::<Versioned3>(1, 2, |count| Ok(Value::from(vec![count])));
+
+    match upgraded(registry) {
+        Err(MachineTreeError::NoMigrationPath { path, from, to }) => {
+            assert_eq!((path.as_str(), from, to), ("/Versioned:v", 1, 3))
+        }
+        _ => panic!("expected NoMigrationPath"),
+    }
+}
+
+#[test]
+fn failed_migrations_name_their_node_and_step() {
+    let registry = ComponentRegistry::new()
+        .register_migration::<Versioned3>(1, 2, |count| Ok(Value::from(vec![count])))
+        .register_migration::<Versioned3>(2, 3, |_| Err("no label".into()));
+
+    match upgraded(registry) {
+        Err(MachineTreeError::MigrationFailed {
+            path,
+            from,
+            to,
+            reason,
+        }) => {
+            assert_eq!((path.as_str(), from, to), ("/Versioned:v", 2, 3));
+            assert_eq!(reason, "no label");
+        }
+        _ => panic!("expected MigrationFailed"),
+    }
+}
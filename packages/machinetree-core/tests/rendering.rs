@@ -0,0 +1,92 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{NodeControl, NodeHost},
+};
+
+// Shared with the test, to step the nodes again and see their steps
+#[derive(Default)]
+struct Probe {
+    value: Cell<u32>,
+    parent: RefCell<Option<NodeHandle>>,
+    child: RefCell<Option<NodeHandle>>,
+    steps: RefCell<Vec<String>>,
+}
+
+impl Probe {
+    fn rerender(handle: &RefCell<Option<NodeHandle>>) {
+        handle.borrow().as_ref().unwrap().rerender().unwrap();
+    }
+}
+
+// Steps only when the parity of its value changes
+struct Parity;
+
+impl Component for Parity {
+    type Input = (u32, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Parity
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (value, probe): &Self::Input) -> Vec<Seed> {
+        probe.child.replace(Some(control.handle()));
+        probe.steps.borrow_mut().push(format!("child {}", value));
+        vec![]
+    }
+
+    fn should_step(&self, (old_value, _): &Self::Input, (new_value, _): &Self::Input) -> bool {
+        old_value % 2 != new_value % 2
+    }
+}
+
+struct Parent;
+
+impl Component for Parent {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Parent
+    }
+
+    fn step(&mut self, control: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        probe.parent.replace(Some(control.handle()));
+        probe.steps.borrow_mut().push("parent".to_string());
+        let input = (probe.value.get(), probe.clone());
+        vec![Parity::seed(input, "child".to_string())]
+    }
+}
+
+#[test]
+fn children_whose_should_step_is_false_are_skipped() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Parent::seed(probe.clone(), "parent".to_string()));
+    host.run_until_idle();
+    assert_eq!(probe.steps.take(), ["parent", "child 0"]);
+
+    probe.value.set(2);
+    Probe::rerender(&probe.parent);
+    let report = host.run_until_idle();
+    assert_eq!(probe.steps.take(), ["parent"]);
+    assert_eq!(report.rendered_keys.len(), 1);
+
+    probe.value.set(3);
+    Probe::rerender(&probe.parent);
+    host.run_until_idle();
+    assert_eq!(probe.steps.take(), ["parent", "child 3"]);
+
+    // A skipped child still holds its latest input
+    probe.value.set(5);
+    Probe::rerender(&probe.parent);
+    host.run_until_idle();
+    Probe::rerender(&probe.child);
+    host.run_until_idle();
+    assert_eq!(probe.steps.take(), ["parent", "child 5"]);
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+use machinetree_core::{
+    key::Seed,
+    node::Component,
+    node_host::{NodeControl, NodeHost, RUN_REPORT_LIMIT},
+};
+
+// Steps again after every step
+struct Busy;
+
+impl Component for Busy {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Busy
+    }
+
+    fn step(&mut self, control: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        control.rerender();
+        vec![]
+    }
+}
+
+#[test]
+fn run_times_out_while_work_keeps_coming() {
+    let mut host = NodeHost::make_root(Busy::seed((), "busy".to_string()));
+    let started = Instant::now();
+    let report = host.run(Some(Duration::from_millis(50)));
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(report.rendered_keys.len(), RUN_REPORT_LIMIT);
+}
+
+struct Idle;
+
+impl Component for Idle {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Idle
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+#[test]
+fn shutdown_requests_are_consumed_where_observed() {
+    let mut host = NodeHost::make_root(Idle::seed((), "idle".to_string()));
+    let shutdown_handle = host.shutdown_handle();
+
+    // Dropped by run_until_idle
+    shutdown_handle.shutdown().unwrap();
+    host.run_until_idle();
+    let started = Instant::now();
+    host.run(Some(Duration::from_millis(50)));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    // Stops a single run
+    shutdown_handle.shutdown().unwrap();
+    host.run(None);
+    let started = Instant::now();
+    host.run(Some(Duration::from_millis(50)));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
@@ -0,0 +1,114 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{
+        scheduler::{LifoScheduler, PriorityScheduler, Scheduler, TreeDepthScheduler},
+        NodeControl, NodeHost,
+    },
+};
+
+// Shared with the test, to rerender nodes and see the order of their steps
+#[derive(Default)]
+struct Probe {
+    steps: RefCell<Vec<String>>,
+    handles: RefCell<HashMap<String, NodeHandle>>,
+}
+
+// root
+// ├── a
+// └── b
+//     └── b1
+fn children_of(name: &str) -> &'static [&'static str] {
+    match name {
+        "root" => &["a", "b"],
+        "b" => &["b1"],
+        _ => &[],
+    }
+}
+
+fn priority_of(name: &str) -> i32 {
+    match name {
+        "a" | "b1" => 1,
+        "b" => 5,
+        _ => 0,
+    }
+}
+
+struct Node;
+
+impl Component for Node {
+    type Input = (String, Rc<Probe>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Node
+    }
+
+    fn step(&mut self, control: &mut NodeControl, (name, probe): &Self::Input) -> Vec<Seed> {
+        control.set_priority(priority_of(name));
+        probe.steps.borrow_mut().push(name.clone());
+        probe
+            .handles
+            .borrow_mut()
+            .insert(name.clone(), control.handle());
+
+        children_of(name)
+            .iter()
+            .map(|child| Node::seed((child.to_string(), probe.clone()), child.to_string()))
+            .collect()
+    }
+
+    // Only rerenders step a node, so that each of them steps one node
+    fn should_step(&self, _: &Self::Input, _: &Self::Input) -> bool {
+        false
+    }
+}
+
+// Steps of the nodes rerendered in `order`, through the scheduler
+fn steps_after_rerenders(scheduler: impl Scheduler + 'static, order: &[&str]) -> Vec<String> {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root_with_scheduler(
+        Node::seed(("root".to_string(), probe.clone()), "root".to_string()),
+        scheduler,
+    );
+    host.run_until_idle();
+    probe.steps.take();
+
+    order.iter().for_each(|name| {
+        probe.handles.borrow()[*name].rerender().unwrap();
+    });
+    host.run_until_idle();
+
+    probe.steps.take()
+}
+
+#[test]
+fn lifo_steps_the_latest_work_first() {
+    assert_eq!(
+        steps_after_rerenders(LifoScheduler::default(), &["a", "b", "b1"]),
+        ["b1", "b", "a"]
+    );
+}
+
+#[test]
+fn tree_depth_orders_by_depth_then_first_in_first_out() {
+    let order = ["b1", "a", "root", "b"];
+    assert_eq!(
+        steps_after_rerenders(TreeDepthScheduler::shallowest_first(), &order),
+        ["root", "a", "b", "b1"]
+    );
+    assert_eq!(
+        steps_after_rerenders(TreeDepthScheduler::deepest_first(), &order),
+        ["b1", "a", "b", "root"]
+    );
+}
+
+#[test]
+fn priority_orders_by_priority_then_first_in_first_out() {
+    assert_eq!(
+        steps_after_rerenders(PriorityScheduler::default(), &["root", "b1", "a", "b"]),
+        ["b", "b1", "a", "root"]
+    );
+}
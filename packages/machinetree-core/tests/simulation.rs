@@ -0,0 +1,92 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, Mailbox},
+    node_host::{NodeControl, NodeHost},
+};
+
+#[derive(Default)]
+struct Recorder {
+    // Names of the stepped nodes, in order
+    trace: RefCell<Vec<String>>,
+    mailboxes: RefCell<Vec<Mailbox<()>>>,
+}
+
+// Steps every `period` and on every message
+struct Ticker {
+    started: bool,
+}
+
+impl Component for Ticker {
+    type Input = (String, Duration, Rc<Recorder>);
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Ticker { started: false }
+    }
+
+    fn step(&mut self, control: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        let (name, period, recorder) = input;
+        if !self.started {
+            self.started = true;
+            control.set_interval(*period);
+            recorder
+                .mailboxes
+                .borrow_mut()
+                .push(control.mailbox::<Ticker>().unwrap());
+        }
+        recorder.trace.borrow_mut().push(name.clone());
+        vec![]
+    }
+}
+
+struct Tickers;
+
+impl Component for Tickers {
+    type Input = Rc<Recorder>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Tickers
+    }
+
+    fn step(&mut self, _: &mut NodeControl, recorder: &Self::Input) -> Vec<Seed> {
+        (1..=4)
+            .map(|index| {
+                let name = format!("ticker{}", index);
+                let period = Duration::from_millis(index * 3);
+                Ticker::seed((name.clone(), period, recorder.clone()), name)
+            })
+            .collect()
+    }
+}
+
+fn trace_of(seed: u64) -> Vec<String> {
+    let recorder = Rc::new(Recorder::default());
+    let mut host = NodeHost::builder()
+        .simulation(seed)
+        .root(Tickers::seed(recorder.clone(), "tickers".to_string()));
+    host.run_until_idle();
+
+    for _ in 0..10 {
+        recorder
+            .mailboxes
+            .borrow()
+            .iter()
+            .for_each(|mailbox| mailbox.send(()).unwrap());
+        host.advance_time(Duration::from_millis(7)).unwrap();
+    }
+
+    let trace = recorder.trace.borrow().clone();
+    trace
+}
+
+#[test]
+fn a_seed_replays_the_same_trace() {
+    let trace = trace_of(7);
+    assert!(trace.len() > 40);
+    assert_eq!(trace, trace_of(7));
+    assert_eq!(trace_of(42), trace_of(42));
+    assert_ne!(trace, trace_of(42));
+}
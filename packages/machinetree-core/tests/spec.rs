@@ -0,0 +1,51 @@
+use machinetree_core::{error::MachineTreeError, spec::TreeSpec};
+
+fn invalid_path(parsed: Result<TreeSpec, MachineTreeError>) -> String {
+    match parsed {
+        Err(MachineTreeError::InvalidSpec { path, .. }) => path,
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("the document is invalid"),
+    }
+}
+
+#[test]
+fn parse_errors_point_at_the_field() {
+    let json = r#"{
+        "component": "Group",
+        "key": "root",
+        "children": [
+            { "component": "Group", "key": "first" },
+            { "component": "Group", "key": 2 }
+        ]
+    }"#;
+    assert_eq!(invalid_path(TreeSpec::from_json(json)), "$.children[1].key");
+
+    let ron = r#"(
+        component: "Group",
+        key: "root",
+        children: [
+            (component: "Group", key: "first", children: [(component: "Group")]),
+        ],
+    )"#;
+    assert_eq!(
+        invalid_path(TreeSpec::from_ron(ron)),
+        "$.children[0].children[0]"
+    );
+
+    assert_eq!(
+        invalid_path(TreeSpec::from_json(r#"{ "key": "root" }"#)),
+        "$"
+    );
+}
+
+#[test]
+fn ron_inputs_may_be_structs() {
+    let spec = TreeSpec::from_ron(
+        r#"(component: "Worker", key: "emails", input: (queue: "emails", retries: 3))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        spec.input,
+        serde_json::json!({ "queue": "emails", "retries": 3 })
+    );
+}
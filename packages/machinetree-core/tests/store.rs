@@ -0,0 +1,178 @@
+use std::{fs::OpenOptions, io::Write, path::PathBuf, sync::Arc};
+
+use machinetree_core::{
+    key::Seed,
+    node::Component,
+    node_host::{NodeControl, NodeHost},
+    persistence::{ComponentRegistry, FileStore, PersistentComponent},
+    spec::TreeSpec,
+};
+
+mod common;
+
+use common::registry;
+
+fn spec(input: &str) -> TreeSpec {
+    TreeSpec::from_json(&format!(
+        r#"{{
+            "component": "Group",
+            "key": "root",
+            "children": [{{ "component": "Counter", "key": "counter", "input": "{}" }}]
+        }}"#,
+        input
+    ))
+    .unwrap()
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("machinetree-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn recovery_replays_the_log_up_to_a_torn_write() {
+    let dir = scratch_dir("recovery");
+    let store = FileStore::open(&dir).unwrap().checkpoint_interval(100);
+    let mut host = NodeHost::builder()
+        .registry(registry())
+        .store(store)
+        .spec(&spec("a"))
+        .unwrap();
+    host.run_until_idle();
+    for input in ["b", "c"] {
+        host.reload(&spec(input)).unwrap();
+        assert!(host.run_until_idle().storage_failures.is_empty());
+    }
+    let expected = host.snapshot().unwrap();
+    drop(host);
+
+    // Both reloads went to the log rather than to a checkpoint
+    let logged = std::fs::read_to_string(dir.join("wal.jsonl")).unwrap();
+    assert_eq!(logged.lines().count(), 2);
+
+    // The process stopped in the middle of writing the next pass
+    let mut log = OpenOptions::new()
+        .append(true)
+        .open(dir.join("wal.jsonl"))
+        .unwrap();
+    log.write_all(br#"{"sequence":99,"updated":[{"id""#)
+        .unwrap();
+    drop(log);
+
+    let mut recovered = NodeHost::recover(&dir, registry()).unwrap();
+    assert_eq!(recovered.snapshot().unwrap(), expected);
+
+    // The recovered host keeps logging its passes
+    recovered.reload(&spec("d")).unwrap();
+    assert!(recovered.run_until_idle().storage_failures.is_empty());
+    let expected = recovered.snapshot().unwrap();
+    drop(recovered);
+
+    let recovered = NodeHost::recover(&dir, registry()).unwrap();
+    assert_eq!(recovered.snapshot().unwrap(), expected);
+    drop(recovered);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// Never steps again once mounted
+struct Sticky;
+
+impl Component for Sticky {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Sticky
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn should_step(&self, _: &Self::Input, _: &Self::Input) -> bool {
+        false
+    }
+}
+
+impl PersistentComponent for Sticky {
+    type State = ();
+
+    fn save_state(&self) -> Self::State {}
+
+    fn restore_state(_: &Self::Input, _: Self::State) -> Self {
+        Sticky
+    }
+}
+
+// Passes its input to a Sticky child
+struct Holder;
+
+impl Component for Holder {
+    type Input = String;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Holder
+    }
+
+    fn step(&mut self, _: &mut NodeControl, input: &Self::Input) -> Vec<Seed> {
+        vec![Sticky::seed(input.clone(), "sticky".to_string())]
+    }
+}
+
+impl PersistentComponent for Holder {
+    type State = ();
+
+    fn save_state(&self) -> Self::State {}
+
+    fn restore_state(_: &Self::Input, _: Self::State) -> Self {
+        Holder
+    }
+}
+
+fn recovered_after_reload(name: &str, component: &str) -> (Vec<u8>, Vec<u8>) {
+    let registry = Arc::new(
+        ComponentRegistry::new()
+            .register_persistent::<Holder>("Holder")
+            .register_persistent::<Sticky>("Sticky"),
+    );
+    let spec = |input: &str| {
+        TreeSpec::from_json(&format!(
+            r#"{{ "component": "{}", "key": "root", "input": "{}" }}"#,
+            component, input
+        ))
+        .unwrap()
+    };
+
+    let dir = scratch_dir(name);
+    let store = FileStore::open(&dir).unwrap().checkpoint_interval(100);
+    let mut host = NodeHost::builder()
+        .registry(registry.clone())
+        .store(store)
+        .spec(&spec("a"))
+        .unwrap();
+    host.run_until_idle();
+    host.reload(&spec("b")).unwrap();
+    assert!(host.run_until_idle().storage_failures.is_empty());
+    let live = host.snapshot().unwrap();
+    drop(host);
+
+    let recovered = NodeHost::recover(&dir, registry)
+        .unwrap()
+        .snapshot()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    (live, recovered)
+}
+
+#[test]
+fn recovery_keeps_the_inputs_of_nodes_that_skipped_their_step() {
+    let (live, recovered) = recovered_after_reload("skipped-child", "Holder");
+    assert!(String::from_utf8_lossy(&live).contains(r#""input":"b""#));
+    assert_eq!(recovered, live);
+
+    let (live, recovered) = recovered_after_reload("skipped-root", "Sticky");
+    assert_eq!(recovered, live);
+}
@@ -0,0 +1,173 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+
+use machinetree_core::{
+    components::{ErrorBoundary, ErrorBoundaryInput, Supervisor, SupervisorInput},
+    key::{Key, Seed},
+    node::{Component, NodeHandle},
+    node_host::{failure::StepError, NodeControl, NodeHost},
+};
+
+const WORKERS: [&str; 3] = ["a", "b", "c"];
+
+// Shared with the test, to fail a worker on its next step
+#[derive(Default)]
+struct Probe {
+    constructed: RefCell<Vec<String>>,
+    failing: RefCell<Option<String>>,
+    handles: RefCell<HashMap<String, NodeHandle>>,
+}
+
+impl Probe {
+    fn fail(&self, name: &str) {
+        self.failing.replace(Some(name.to_string()));
+        self.handles.borrow()[name].rerender().unwrap();
+    }
+}
+
+struct Worker;
+
+impl Component for Worker {
+    type Input = (String, Rc<Probe>);
+    type Message = ();
+
+    fn construct((name, probe): &Self::Input) -> Self {
+        probe.constructed.borrow_mut().push(name.clone());
+        Worker
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn try_step(
+        &mut self,
+        control: &mut NodeControl,
+        (name, probe): &Self::Input,
+    ) -> Result<Vec<Seed>, StepError> {
+        probe
+            .handles
+            .borrow_mut()
+            .insert(name.clone(), control.handle());
+        if probe.failing.borrow().as_ref() == Some(name) {
+            probe.failing.take();
+            return Err(format!("{} failed", name).into());
+        }
+        Ok(vec![])
+    }
+}
+
+fn workers(probe: &Rc<Probe>) -> Vec<Seed> {
+    WORKERS
+        .iter()
+        .map(|name| Worker::seed((name.to_string(), probe.clone()), name.to_string()))
+        .collect()
+}
+
+// Names of the workers among `keys`
+fn names(keys: &[Key]) -> Vec<&'static str> {
+    keys.iter()
+        .filter_map(|key| {
+            let name = key.debug_attempt_get_name();
+            WORKERS
+                .into_iter()
+                .find(|worker| name.contains(&format!("\\\"{}\\\"", worker)))
+        })
+        .collect()
+}
+
+fn restart_after_failure(
+    input: fn(Vec<Seed>) -> SupervisorInput,
+) -> (Vec<String>, Vec<&'static str>) {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::make_root(Supervisor::seed(
+        input(workers(&probe)),
+        "supervisor".to_string(),
+    ));
+    host.run_until_idle();
+    assert_eq!(probe.constructed.take(), WORKERS);
+
+    probe.fail("b");
+    let report = host.run_until_idle();
+
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.failures[0].boundary.is_some());
+    assert_eq!(report.restarts.len(), 1);
+    let restart = &report.restarts[0];
+    assert_eq!(names(std::slice::from_ref(&restart.failure.key)), ["b"]);
+    assert_eq!(report.unlinked_node_pairs.len(), restart.restarted.len());
+
+    (probe.constructed.take(), names(&restart.restarted))
+}
+
+#[test]
+fn one_for_one_restarts_the_failing_child() {
+    let (constructed, restarted) = restart_after_failure(SupervisorInput::one_for_one);
+    assert_eq!(constructed, ["b"]);
+    assert_eq!(restarted, ["b"]);
+}
+
+#[test]
+fn one_for_all_restarts_every_child() {
+    let (constructed, restarted) = restart_after_failure(SupervisorInput::one_for_all);
+    assert_eq!(constructed, ["a", "b", "c"]);
+    assert_eq!(restarted, ["a", "b", "c"]);
+}
+
+#[test]
+fn rest_for_one_restarts_the_failing_child_and_the_children_after_it() {
+    let (constructed, restarted) = restart_after_failure(SupervisorInput::rest_for_one);
+    assert_eq!(constructed, ["b", "c"]);
+    assert_eq!(restarted, ["b", "c"]);
+}
+
+struct Supervised;
+
+impl Component for Supervised {
+    type Input = Rc<Probe>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Supervised
+    }
+
+    fn step(&mut self, _: &mut NodeControl, probe: &Self::Input) -> Vec<Seed> {
+        let supervisor = Supervisor::seed(
+            SupervisorInput::one_for_one(workers(probe)).with_intensity(1, Duration::from_secs(5)),
+            "supervisor".to_string(),
+        );
+        vec![ErrorBoundary::seed(
+            ErrorBoundaryInput::new(vec![supervisor], |_| vec![]),
+            "boundary".to_string(),
+        )]
+    }
+}
+
+#[test]
+fn failures_past_the_intensity_escalate_to_the_parent() {
+    let probe = Rc::new(Probe::default());
+    let mut host = NodeHost::builder()
+        .manual_clock(Default::default())
+        .root(Supervised::seed(probe.clone(), "root".to_string()));
+    host.run_until_idle();
+
+    // A restart is allowed within the window
+    probe.fail("a");
+    let report = host.run_until_idle();
+    assert_eq!(report.restarts.len(), 1);
+
+    // The earlier restart left the window
+    host.advance_time(Duration::from_secs(6)).unwrap();
+    probe.fail("a");
+    let report = host.run_until_idle();
+    assert_eq!(report.restarts.len(), 1);
+
+    // The second restart within the window goes to the error boundary instead
+    probe.constructed.take();
+    probe.fail("a");
+    let report = host.run_until_idle();
+    assert!(report.restarts.is_empty());
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.failures[0].boundary.is_some());
+    assert_eq!(report.unlinked_node_pairs.len(), 4);
+    assert!(probe.constructed.take().is_empty());
+}
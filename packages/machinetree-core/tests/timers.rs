@@ -0,0 +1,154 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use machinetree_core::{
+    key::Seed,
+    node::{Component, NodeHandle},
+    node_host::{
+        clock::{Clock, ClockContext, ManualClock},
+        NodeControl, NodeHost,
+    },
+};
+
+struct Ticking;
+
+impl Component for Ticking {
+    // Steps so far
+    type Input = Rc<Cell<u32>>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Ticking
+    }
+
+    fn on_mount(&mut self, control: &mut NodeControl) {
+        control.set_interval(Duration::ZERO);
+    }
+
+    fn step(&mut self, _: &mut NodeControl, steps: &Self::Input) -> Vec<Seed> {
+        steps.set(steps.get() + 1);
+        vec![]
+    }
+}
+
+#[test]
+fn zero_intervals_tick_at_the_minimum_period() {
+    let clock = Arc::new(ManualClock::new(Instant::now()));
+    let steps = Rc::new(Cell::new(0));
+    let mut host = NodeHost::builder()
+        .manual_clock(clock)
+        .root(Ticking::seed(steps.clone(), "ticking".to_string()));
+    host.run_until_idle();
+    assert_eq!(steps.get(), 1);
+
+    host.advance_time(Duration::from_millis(10)).unwrap();
+    assert_eq!(steps.get(), 11);
+}
+
+struct Leaving;
+
+impl Component for Leaving {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Leaving
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+
+    fn on_unmount(&mut self, control: &mut NodeControl) {
+        control.set_timeout(Duration::from_secs(1));
+    }
+}
+
+// Steps Leaving while shown, shared with the test to step it again
+#[derive(Default)]
+struct Switch {
+    hidden: Cell<bool>,
+    handle: RefCell<Option<NodeHandle>>,
+}
+
+struct Toggle;
+
+impl Component for Toggle {
+    type Input = Rc<Switch>;
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Toggle
+    }
+
+    fn step(&mut self, control: &mut NodeControl, switch: &Self::Input) -> Vec<Seed> {
+        switch.handle.replace(Some(control.handle()));
+        match switch.hidden.get() {
+            true => vec![],
+            false => vec![Leaving::seed((), "leaving".to_string())],
+        }
+    }
+}
+
+#[test]
+fn timers_set_while_unmounting_are_cancelled() {
+    let clock = Arc::new(ManualClock::new(Instant::now()));
+    let switch = Rc::new(Switch::default());
+    let mut host = NodeHost::builder()
+        .manual_clock(clock)
+        .root(Toggle::seed(switch.clone(), "toggle".to_string()));
+    host.run_until_idle();
+    assert_eq!(host.next_timer_deadline(), None);
+
+    switch.hidden.set(true);
+    switch.handle.borrow().as_ref().unwrap().rerender().unwrap();
+    host.run_until_idle();
+    assert_eq!(host.next_timer_deadline(), None);
+}
+
+struct Waiting;
+
+impl Component for Waiting {
+    type Input = ();
+    type Message = ();
+
+    fn construct(_: &Self::Input) -> Self {
+        Waiting
+    }
+
+    fn on_mount(&mut self, control: &mut NodeControl) {
+        control.set_timeout(Duration::from_secs(1));
+    }
+
+    fn step(&mut self, _: &mut NodeControl, _: &Self::Input) -> Vec<Seed> {
+        vec![]
+    }
+}
+
+#[test]
+fn a_provided_clock_drives_the_timers() {
+    let clock = Arc::new(ManualClock::new(Instant::now() + Duration::from_secs(3600)));
+    let start = clock.now();
+    let mut host = NodeHost::builder()
+        .provide::<ClockContext>(clock.clone())
+        .root(Waiting::seed((), "waiting".to_string()));
+    host.run_until_idle();
+    assert_eq!(
+        host.next_timer_deadline(),
+        Some(start + Duration::from_secs(1))
+    );
+
+    // Not the system clock, so the run does not sleep until the timer
+    let started = Instant::now();
+    host.run(Some(Duration::from_millis(20)));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(host.next_timer_deadline().is_some());
+
+    clock.advance(Duration::from_secs(1));
+    host.run_until_idle();
+    assert_eq!(host.next_timer_deadline(), None);
+}